@@ -1,4 +1,6 @@
-use parcomb::parser::{lst_sep_empt, ParseResult, Parser};
+use parcomb::lazy_parser;
+use parcomb::parser::{alt, eof, lst_sep_empt, parse, seq, Parser};
+use parcomb::report::Report;
 use parcomb::string_parser::{lit, lit_sp, reg, spaces};
 
 use std::collections::HashMap;
@@ -15,88 +17,67 @@ enum JsonValue {
     Null,
 }
 
-type JsonResult<'a> = ParseResult<&'a str, JsonValue, String>;
+lazy_parser! {
+    fn null_p(&str) -> JsonValue = lit("null").map(|_| JsonValue::Null);
 
-fn null_p<'a>(input: &'a str) -> JsonResult<'a> {
-    lit("null").map(|_| JsonValue::Null).parse(input)
-}
-
-fn bool_p<'a>(input: &'a str) -> JsonResult<'a> {
-    let true_p = lit("true").map(|_| JsonValue::Bool(true));
-    let false_p = lit("false").map(|_| JsonValue::Bool(false));
-
-    true_p.or(false_p).parse(input)
-}
-
-fn number_p<'a>(input: &'a str) -> JsonResult<'a> {
-    let sign_pat = r"-";
-    let int_pat = r"0|([123456789]\d*)";
-    let frag_pat = r"\.\d+";
-    let exp_pat = r"[eE][\+-]\d+";
-
-    let ptn = format!("({sign_pat})?({int_pat})({frag_pat})?({exp_pat})?");
-    let par = reg(&ptn).map(|s| JsonValue::Number(f64::from_str(&s).unwrap()));
-
-    par.parse(input)
-}
-
-fn raw_string_p<'a>(input: &'a str) -> ParseResult<&'a str, String, String> {
-    let str_char_ptn = r#"[^\\"]"#;
-    let esc_char_ptn = r#"\\[\\/"bfnrt]"#;
-    let utf16_char_ptn = r#"\\u[a-fA-F0-9]{4}"#; // todo: should conver to u16
+    fn bool_p(&str) -> JsonValue = {
+        let true_p = lit("true").map(|_| JsonValue::Bool(true));
+        let false_p = lit("false").map(|_| JsonValue::Bool(false));
 
-    let json_str_ptn = format!(r#""(({str_char_ptn})|({esc_char_ptn})|({utf16_char_ptn}))*""#);
+        true_p.or(false_p)
+    };
 
-    let par = reg(&json_str_ptn);
+    fn number_p(&str) -> JsonValue = {
+        let sign_pat = r"-";
+        let int_pat = r"0|([123456789]\d*)";
+        let frag_pat = r"\.\d+";
+        let exp_pat = r"[eE][\+-]\d+";
 
-    par.parse(input)
-}
+        let ptn = format!("({sign_pat})?({int_pat})({frag_pat})?({exp_pat})?");
+        reg(&ptn)
+            .try_map(|s| f64::from_str(&s).map(JsonValue::Number))
+            .label("number")
+    };
 
-fn string_p<'a>(input: &'a str) -> JsonResult<'a> {
-    raw_string_p.map(|s| JsonValue::String(s)).parse(input)
-}
+    fn raw_string_p(&str) -> String = {
+        let str_char_ptn = r#"[^\\"]"#;
+        let esc_char_ptn = r#"\\[\\/"bfnrt]"#;
+        let utf16_char_ptn = r#"\\u[a-fA-F0-9]{4}"#; // todo: should conver to u16
 
-fn array_p<'a>(input: &'a str) -> JsonResult<'a> {
-    let par = lit_sp("[")
-        .and_r(lst_sep_empt(value_p, lit_sp(",")))
-        .and_l(lit_sp("]"))
-        .map(|arr| JsonValue::Array(arr));
+        let json_str_ptn = format!(r#""(({str_char_ptn})|({esc_char_ptn})|({utf16_char_ptn}))*""#);
 
-    par.parse(input)
-}
+        reg(&json_str_ptn).label("string")
+    };
 
-fn object_p<'a>(input: &'a str) -> JsonResult<'a> {
-    let kv_member = raw_string_p.and_l(lit_sp(":")).and(value_p);
-    let kv_members = lst_sep_empt(kv_member, lit_sp(","));
+    fn string_p(&str) -> JsonValue = raw_string_p.map(JsonValue::String);
 
-    let obj = lit_sp("{").and_r(kv_members).and_l(lit_sp("}")).map(|kvs| {
-        let hmap: HashMap<String, JsonValue> = kvs.into_iter().collect();
-        JsonValue::Object(hmap)
-    });
+    fn array_p(&str) -> JsonValue = {
+        let elms = lst_sep_empt(value_p, lit_sp(","));
 
-    obj.parse(input)
-}
+        lit_sp("[")
+            .and_r(elms.and_l(lit_sp("]")).cut())
+            .map(JsonValue::Array)
+            .context("while parsing array")
+    };
 
-fn value_p<'a>(input: &'a str) -> JsonResult<'a> {
-    let par = null_p
-        .or(bool_p)
-        .or(number_p)
-        .or(string_p)
-        .or(array_p)
-        .or(object_p);
+    fn object_p(&str) -> JsonValue = {
+        let kv_member = seq((raw_string_p, lit_sp(":"), value_p))
+            .map(|(key, _, value)| (key, value))
+            .context("while parsing object member");
+        let kv_members = lst_sep_empt(kv_member, lit_sp(","));
 
-    par.parse(input)
-}
+        lit_sp("{")
+            .and_r(kv_members.and_l(lit_sp("}")).cut())
+            .map(|kvs| {
+                let hmap: HashMap<String, JsonValue> = kvs.into_iter().collect();
+                JsonValue::Object(hmap)
+            })
+            .context("while parsing object")
+    };
 
-fn json_p<'a>(input: &'a str) -> JsonResult<'a> {
-    let par = spaces().and_r(value_p).and_l(spaces());
-    let (o, i) = par.parse(input)?;
+    fn value_p(&str) -> JsonValue = alt((null_p, bool_p, number_p, string_p, array_p, object_p));
 
-    if i.len() > 0 {
-        return Err(format!("invalid json"));
-    }
-
-    return Ok((o, i));
+    fn json_p(&str) -> JsonValue = spaces().and_r(value_p).and_l(spaces()).and_l(eof());
 }
 
 fn main() {
@@ -116,5 +97,11 @@ fn main() {
         }
     "#;
 
-    println!("{:#?}", json_p.parse(input));
+    println!("{:#?}", parse(json_p, input));
+
+    let broken = r#"{ "Array": [1, 2, tru] }"#;
+    match parse(json_p, broken) {
+        Ok((json, _)) => println!("{:#?}", json),
+        Err(e) => print!("{}", Report::new(broken, &e).file("broken.json")),
+    }
 }
@@ -0,0 +1,390 @@
+use crate::parser::{self, ParseError, ParseResult, Parser, ParserError, TakeParser};
+use regex::bytes::Regex;
+
+pub struct TagParser {
+    tag: Vec<u8>,
+}
+
+impl<'a> Parser<'a, [u8], &'a [u8], ParseError> for TagParser {
+    fn parse(&self, input: &'a [u8]) -> ParseResult<&'a [u8], &'a [u8]> {
+        if input.starts_with(&self.tag) {
+            return Ok(input.split_at(self.tag.len()));
+        }
+
+        Err(expected(input, &describe(&self.tag)))
+    }
+}
+
+pub struct TakeUntilParser {
+    pattern: Vec<u8>,
+}
+
+impl<'a> Parser<'a, [u8], &'a [u8], ParseError> for TakeUntilParser {
+    fn parse(&self, input: &'a [u8]) -> ParseResult<&'a [u8], &'a [u8]> {
+        let found = if self.pattern.is_empty() {
+            Some(0)
+        } else {
+            input
+                .windows(self.pattern.len())
+                .position(|w| w == self.pattern)
+        };
+
+        match found {
+            Some(end) => Ok(input.split_at(end)),
+            None => Err(expected(&input[input.len()..], &describe(&self.pattern))),
+        }
+    }
+}
+
+pub struct ByteClassParser {
+    class: [bool; 256],
+    expected: String,
+}
+
+impl<'a> Parser<'a, [u8], u8, ParseError> for ByteClassParser {
+    fn parse(&self, input: &'a [u8]) -> ParseResult<&'a [u8], u8> {
+        match input.split_first() {
+            Some((&b, rest)) if self.class[b as usize] => Ok((b, rest)),
+            _ => Err(expected(input, &self.expected)),
+        }
+    }
+}
+
+pub struct RegexParser {
+    re: Regex,
+    expected: String,
+}
+
+impl<'a> Parser<'a, [u8], &'a [u8], ParseError> for RegexParser {
+    fn parse(&self, input: &'a [u8]) -> ParseResult<&'a [u8], &'a [u8]> {
+        match self.re.find(input) {
+            None => Err(expected(input, &self.expected)),
+            Some(mat) => Ok(input.split_at(mat.end())),
+        }
+    }
+}
+
+/// Creates an error at the start of `input`, expecting `expected`. The found
+/// byte is escaped like in [`describe`], rather than shown as a number.
+fn expected(input: &[u8], expected: &str) -> ParseError {
+    let mut err: ParseError = ParserError::expected(input, expected);
+    err.found = input
+        .first()
+        .map_or_else(String::new, |b| b.escape_ascii().to_string());
+
+    err
+}
+
+/// Formats `bytes` like a byte string literal, e.g. `b"GET\r\n"`.
+fn describe(bytes: &[u8]) -> String {
+    format!("b\"{}\"", bytes.escape_ascii())
+}
+
+/// # Example:
+/// ```
+/// use parcomb::bytes_parser::*;
+/// use parcomb::parser::Parser;
+///
+/// let par = tag(b"GET ");
+/// let inp = b"GET /index.html";
+/// assert_eq!((&b"GET "[..], &b"/index.html"[..]), par.parse(inp).unwrap());
+///
+/// let err = par.parse(b"PUT /").unwrap_err();
+/// assert_eq!(vec![r#"b"GET ""#], err.expected);
+/// ```
+pub fn tag(tag: &[u8]) -> TagParser {
+    TagParser { tag: tag.to_vec() }
+}
+
+/// Matches exactly `n` bytes.
+///
+/// # Example:
+/// ```
+/// use parcomb::bytes_parser::*;
+/// use parcomb::parser::Parser;
+///
+/// let magic = take(4);
+/// let inp = b"\x7fELF\x02\x01";
+/// assert_eq!((&b"\x7fELF"[..], &b"\x02\x01"[..]), magic.parse(inp).unwrap());
+/// assert!(magic.parse(b"\x7fEL").is_err());
+/// ```
+pub fn take(n: usize) -> TakeParser<[u8], ParseError> {
+    parser::take(n)
+}
+
+/// Matches bytes as long as `pred` holds for them, possibly none.
+///
+/// # Example:
+/// ```
+/// use parcomb::bytes_parser::*;
+/// use parcomb::parser::Parser;
+///
+/// let digits = take_while(|b| b.is_ascii_digit());
+/// assert_eq!((&b"200"[..], &b" OK"[..]), digits.parse(b"200 OK").unwrap());
+/// ```
+pub fn take_while<F>(pred: F) -> impl for<'a> Parser<'a, [u8], &'a [u8], ParseError>
+where
+    F: Fn(u8) -> bool,
+{
+    parser::take_while(move |b: &&u8| pred(**b))
+}
+
+/// Matches the bytes up to the first occurrence of `pattern`, which is left
+/// in the input. Fails if `pattern` doesn't occur.
+///
+/// # Example:
+/// ```
+/// use parcomb::bytes_parser::*;
+/// use parcomb::parser::Parser;
+///
+/// let line = take_until(b"\r\n");
+/// let inp = b"Host: a.org\r\n\r\n";
+/// assert_eq!((&b"Host: a.org"[..], &b"\r\n\r\n"[..]), line.parse(inp).unwrap());
+///
+/// let err = line.parse(b"Host").unwrap_err();
+/// assert_eq!(vec![r#"b"\r\n""#], err.expected);
+/// ```
+pub fn take_until(pattern: &[u8]) -> TakeUntilParser {
+    TakeUntilParser {
+        pattern: pattern.to_vec(),
+    }
+}
+
+/// Matches a single byte out of `bytes`.
+///
+/// # Example:
+/// ```
+/// use parcomb::bytes_parser::*;
+/// use parcomb::parser::Parser;
+///
+/// let hex = one_of(b"0123456789abcdef");
+/// assert_eq!((b'f', &b"g"[..]), hex.parse(b"fg").unwrap());
+///
+/// let err = hex.parse(b"g").unwrap_err();
+/// assert_eq!(vec![r#"one of b"0123456789abcdef""#], err.expected);
+/// ```
+pub fn one_of(bytes: &[u8]) -> ByteClassParser {
+    let mut class = [false; 256];
+    for &b in bytes {
+        class[b as usize] = true;
+    }
+
+    ByteClassParser {
+        class,
+        expected: format!("one of {}", describe(bytes)),
+    }
+}
+
+/// Matches a single byte not out of `bytes`.
+pub fn none_of(bytes: &[u8]) -> ByteClassParser {
+    let mut class = one_of(bytes).class;
+    for b in class.iter_mut() {
+        *b = !*b;
+    }
+
+    ByteClassParser {
+        class,
+        expected: format!("none of {}", describe(bytes)),
+    }
+}
+
+/// Matches the regex `re` on bytes, which need not be valid UTF-8. Use
+/// `(?-u)` in `re` to match arbitrary bytes.
+///
+/// # Example:
+/// ```
+/// use parcomb::bytes_parser::*;
+/// use parcomb::parser::Parser;
+///
+/// let par = reg(r"(?-u)[\x80-\xff]+");
+/// let inp = b"\xde\xad\xbe\xefok";
+/// assert_eq!((&inp[..4], &b"ok"[..]), par.parse(inp).unwrap());
+///
+/// let err = par.parse(b"ok").unwrap_err();
+/// assert_eq!(vec![r"/(?-u)[\x80-\xff]+/"], err.expected);
+/// ```
+pub fn reg(re: &str) -> RegexParser {
+    let re_pattern = format!("^(?:{})", re);
+    let expected = format!("/{}/", re);
+    let re = Regex::new(&re_pattern).unwrap();
+
+    RegexParser { re, expected }
+}
+
+/// Decodes a fixed-width number out of the next `size` bytes.
+pub struct NumberParser<T> {
+    size: usize,
+    decode: fn(&[u8]) -> T,
+}
+
+impl<'a, T> Parser<'a, [u8], T, ParseError> for NumberParser<T> {
+    fn parse(&self, input: &'a [u8]) -> ParseResult<&'a [u8], T> {
+        let (bytes, remain) = take(self.size).parse(input)?;
+
+        Ok(((self.decode)(bytes), remain))
+    }
+}
+
+macro_rules! number_parsers {
+    ($($t:ident: $be:ident, $le:ident;)*) => {
+        $(
+            #[doc = concat!("Matches a big-endian `", stringify!($t), "`.")]
+            pub fn $be() -> NumberParser<$t> {
+                NumberParser {
+                    size: std::mem::size_of::<$t>(),
+                    decode: |bytes| $t::from_be_bytes(bytes.try_into().unwrap()),
+                }
+            }
+
+            #[doc = concat!("Matches a little-endian `", stringify!($t), "`.")]
+            pub fn $le() -> NumberParser<$t> {
+                NumberParser {
+                    size: std::mem::size_of::<$t>(),
+                    decode: |bytes| $t::from_le_bytes(bytes.try_into().unwrap()),
+                }
+            }
+        )*
+    };
+}
+
+number_parsers! {
+    u16: be_u16, le_u16;
+    u32: be_u32, le_u32;
+    u64: be_u64, le_u64;
+    u128: be_u128, le_u128;
+    i16: be_i16, le_i16;
+    i32: be_i32, le_i32;
+    i64: be_i64, le_i64;
+    i128: be_i128, le_i128;
+    f32: be_f32, le_f32;
+    f64: be_f64, le_f64;
+}
+
+/// Matches a single byte.
+///
+/// # Example:
+/// ```
+/// use parcomb::bytes_parser::*;
+/// use parcomb::parser::Parser;
+///
+/// // a length-prefixed list of big-endian u16s
+/// let par = u8().flat_map(|len| be_u16().repeat_n(len as usize));
+/// let inp = b"\x02\x01\x00\x00\x2a\xff";
+/// assert_eq!((vec![256, 42], &b"\xff"[..]), par.parse(inp).unwrap());
+///
+/// let err = par.parse(b"\x02\x01\x00\x00").unwrap_err();
+/// assert_eq!("", err.found);
+/// ```
+pub fn u8() -> NumberParser<u8> {
+    NumberParser {
+        size: 1,
+        decode: |bytes| bytes[0],
+    }
+}
+
+/// Matches a single byte as a two's complement `i8`.
+pub fn i8() -> NumberParser<i8> {
+    NumberParser {
+        size: 1,
+        decode: |bytes| bytes[0] as i8,
+    }
+}
+
+/// Matches an unsigned LEB128 number, the varint encoding of protobuf.
+/// Fails if the number doesn't fit 64 bits.
+///
+/// # Example:
+/// ```
+/// use parcomb::bytes_parser::*;
+/// use parcomb::parser::Parser;
+///
+/// let par = uleb128();
+/// assert_eq!((300, &b"!"[..]), par.parse(b"\xac\x02!").unwrap());
+///
+/// let err = par.parse(b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\x02").unwrap_err();
+/// assert_eq!(vec!["a 64-bit uleb128"], err.expected);
+/// ```
+pub fn uleb128() -> impl for<'a> Parser<'a, [u8], u64, ParseError> {
+    fn uleb128_p(input: &[u8]) -> ParseResult<&[u8], u64> {
+        leb128(input, false)
+    }
+
+    uleb128_p
+}
+
+/// Matches a signed LEB128 number. Fails if the number doesn't fit 64 bits.
+///
+/// # Example:
+/// ```
+/// use parcomb::bytes_parser::*;
+/// use parcomb::parser::Parser;
+///
+/// assert_eq!((-123456, &b""[..]), sleb128().parse(b"\xc0\xbb\x78").unwrap());
+/// ```
+pub fn sleb128() -> impl for<'a> Parser<'a, [u8], i64, ParseError> {
+    fn sleb128_p(input: &[u8]) -> ParseResult<&[u8], i64> {
+        let (n, remain) = leb128(input, true)?;
+
+        Ok((n as i64, remain))
+    }
+
+    sleb128_p
+}
+
+/// Matches a zigzag encoded varint, as protobuf uses for `sint64`, mapping
+/// `0, 1, 2, 3, ...` to `0, -1, 1, -2, ...`.
+///
+/// # Example:
+/// ```
+/// use parcomb::bytes_parser::*;
+/// use parcomb::parser::Parser;
+///
+/// let par = zigzag().repeat();
+/// let inp = b"\x00\x01\x02\x03\xff\x01";
+/// assert_eq!((vec![0, -1, 1, -2, -128], &b""[..]), par.parse(inp).unwrap());
+/// ```
+pub fn zigzag() -> impl for<'a> Parser<'a, [u8], i64, ParseError> {
+    fn zigzag_p(input: &[u8]) -> ParseResult<&[u8], i64> {
+        let (n, remain) = leb128(input, false)?;
+
+        Ok(((n >> 1) as i64 ^ -((n & 1) as i64), remain))
+    }
+
+    zigzag_p
+}
+
+/// Decodes a LEB128 number, sign extending it if `signed`.
+fn leb128(input: &[u8], signed: bool) -> ParseResult<&[u8], u64> {
+    let mut value = 0;
+
+    for (i, &b) in input.iter().enumerate() {
+        let shift = 7 * i as u32;
+        let bits = u64::from(b & 0x7f);
+        let last = b & 0x80 == 0;
+
+        // only the lowest bit of the tenth byte fits, for signed numbers the
+        // others have to extend its sign
+        let fits = match (shift, signed) {
+            (0..=56, _) => true,
+            (63, false) => last && bits <= 1,
+            (63, true) => last && (bits == 0 || bits == 0x7f),
+            _ => false,
+        };
+        if !fits {
+            let width = if signed { "sleb128" } else { "uleb128" };
+            return Err(expected(input, &format!("a 64-bit {}", width)));
+        }
+
+        value |= bits << shift;
+
+        if last {
+            if signed && shift + 7 < 64 && b & 0x40 != 0 {
+                value |= !0 << (shift + 7);
+            }
+
+            return Ok((value, &input[i + 1..]));
+        }
+    }
+
+    Err(ParseError::unexpected(&input[input.len()..]))
+}
@@ -0,0 +1,140 @@
+use super::bytes_parser::*;
+use super::parser::{parse, seq, ParseResult, Parser};
+
+// `GET /path HTTP/1.1\r\n`, with a path that need not be valid UTF-8
+fn request_line_p(input: &[u8]) -> ParseResult<&[u8], (&[u8], &[u8])> {
+    let method = take_while(|b| b.is_ascii_uppercase());
+    let path = take_until(b" ");
+    let version = tag(b"HTTP/").and_r(reg(r"\d\.\d"));
+
+    seq((method, tag(b" "), path, tag(b" "), version, tag(b"\r\n")))
+        .map(|(method, _, path, _, _, _)| (method, path))
+        .parse(input)
+}
+
+#[test]
+fn test_request_line() {
+    let inp = b"GET /caf\xe9 HTTP/1.1\r\nHost: a.org\r\n";
+    let ((method, path), rest) = parse(request_line_p, inp).unwrap();
+
+    assert_eq!(b"GET", method);
+    assert_eq!(b"/caf\xe9", path);
+    assert_eq!(b"Host: a.org\r\n", rest);
+
+    let err = parse(request_line_p, b"GET / HTTP/2\r\n").unwrap_err();
+    assert_eq!((11, 12), (err.offset, err.column));
+    assert_eq!(vec![r"/\d\.\d/"], err.expected);
+    assert_eq!("2", err.found);
+}
+
+#[test]
+fn test_byte_classes() {
+    let hex = one_of(b"0123456789abcdef");
+    let par = tag(b"0x").and_r(hex.repeat1());
+
+    let (digits, rest) = par.parse(b"0x1f!").unwrap();
+    assert_eq!((vec![b'1', b'f'], &b"!"[..]), (digits, rest));
+
+    let not_nul = none_of(b"\0");
+    let cstr = not_nul.repeat().and_l(tag(b"\0"));
+    let (s, rest) = parse(cstr, b"ab\0cd").unwrap();
+    assert_eq!((b"ab".to_vec(), &b"cd"[..]), (s, rest));
+
+    let err = parse(none_of(b"\0"), b"\0").unwrap_err();
+    assert_eq!(vec![r#"none of b"\x00""#], err.expected);
+    assert_eq!(
+        r#"1:1: expected none of b"\x00", found `\x00`"#,
+        err.to_string()
+    );
+}
+
+#[test]
+fn test_reg_alternation_is_anchored() {
+    let par = reg("GET|POST");
+
+    assert_eq!(Ok((&b"POST"[..], &b" /"[..])), par.parse(b"POST /"));
+    let err = par.parse(b"xxxPOST /").unwrap_err();
+    assert_eq!(vec!["/GET|POST/"], err.expected);
+}
+
+#[test]
+fn test_take_until_missing_pattern() {
+    let err = parse(take_until(b"\r\n\r\n"), b"Host: a\r\n").unwrap_err();
+
+    assert_eq!(9, err.offset);
+    assert_eq!("", err.found);
+    assert_eq!(
+        r#"1:10: expected b"\r\n\r\n", found end of input"#,
+        err.to_string()
+    );
+
+    assert_eq!(Ok((&b""[..], &b"ab"[..])), take_until(b"").parse(b"ab"));
+}
+
+#[test]
+fn test_numbers() {
+    let inp = b"\x12\x34\x56\x78";
+
+    assert_eq!(Ok((0x1234, &inp[2..])), be_u16().parse(inp));
+    assert_eq!(Ok((0x3412, &inp[2..])), le_u16().parse(inp));
+    assert_eq!(Ok((0x12345678, &b""[..])), be_u32().parse(inp));
+    assert_eq!(Ok((0x78563412, &b""[..])), le_u32().parse(inp));
+    assert_eq!(Ok((-2, &b""[..])), be_i16().parse(b"\xff\xfe"));
+    assert_eq!(
+        Ok((-2, &b""[..])),
+        le_i64().parse(b"\xfe\xff\xff\xff\xff\xff\xff\xff")
+    );
+    assert_eq!(Ok((1.5, &b""[..])), be_f64().parse(&1.5f64.to_be_bytes()));
+    assert_eq!(
+        Ok((-0.25, &b""[..])),
+        le_f32().parse(&(-0.25f32).to_le_bytes())
+    );
+
+    let err = parse(be_u32(), b"\x00\x01").unwrap_err();
+    assert_eq!("1:3: unexpected end of input", err.to_string());
+}
+
+#[test]
+fn test_varints() {
+    let cases: [(&[u8], u64); 4] = [
+        (b"\x00", 0),
+        (b"\x7f", 127),
+        (b"\x80\x01", 128),
+        (b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\x01", u64::MAX),
+    ];
+    for (inp, n) in cases {
+        assert_eq!(Ok((n, &b""[..])), uleb128().parse(inp));
+    }
+
+    assert_eq!(Ok((-1, &b""[..])), sleb128().parse(b"\x7f"));
+    assert_eq!(Ok((63, &b""[..])), sleb128().parse(b"\x3f"));
+    assert_eq!(Ok((-64, &b""[..])), sleb128().parse(b"\x40"));
+    let min = b"\x80\x80\x80\x80\x80\x80\x80\x80\x80\x7f";
+    assert_eq!(Ok((i64::MIN, &b""[..])), sleb128().parse(min));
+    let err = sleb128()
+        .parse(b"\x80\x80\x80\x80\x80\x80\x80\x80\x80\x01")
+        .unwrap_err();
+    assert_eq!(vec!["a 64-bit sleb128"], err.expected);
+
+    let max = b"\xfe\xff\xff\xff\xff\xff\xff\xff\xff\x01";
+    assert_eq!(Ok((i64::MAX, &b""[..])), zigzag().parse(max));
+
+    let err = parse(uleb128(), b"\x80\x80").unwrap_err();
+    assert_eq!("1:3: unexpected end of input", err.to_string());
+}
+
+// a TLV record: tag byte, varint length and that many big-endian u32 values
+#[test]
+fn test_tlv_records() {
+    let record = seq((
+        u8(),
+        uleb128().flat_map(|len| be_u32().repeat_n(len as usize)),
+    ));
+    let records = record.repeat();
+
+    let inp = b"\x01\x01\x00\x00\x00\x2a\x02\x02\xff\xff\xff\xff\x00\x00\x00\x01";
+    let (out, rest) = parse(records, inp).unwrap();
+
+    assert_eq!(vec![(1, vec![42]), (2, vec![u32::MAX, 1])], out);
+    assert!(rest.is_empty());
+}
@@ -1,8 +1,16 @@
+pub mod bytes_parser;
 pub mod parser;
+pub mod report;
 pub mod string_parser;
 
+#[cfg(test)]
+mod bytes_parser_test;
+
 #[cfg(test)]
 mod parser_test;
 
+#[cfg(test)]
+mod report_test;
+
 #[cfg(test)]
 mod string_parser_test;
@@ -1,548 +1,3861 @@
+use std::any::Any;
+use std::cell::{OnceCell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
 use std::marker::PhantomData;
-use std::rc::Rc;
+use std::ops::{Bound, RangeBounds};
+use std::rc::{Rc, Weak};
+use std::sync::{self, Arc, OnceLock};
 
-pub type ParseResult<I, O, E = String> = Result<(O, I), E>;
+pub type ParseResult<I, O, E = ParseError> = Result<(O, I), E>;
+
+/// The default error type of the built-in parsers.
+///
+/// A parser only sees the part of the input that is left, so a fresh error
+/// has no position yet. The top-level [`parse`] function resolves `offset`,
+/// `line` and `column` against the whole input; until then they are `0`, `1`
+/// and `1`, and [`ParseError::is_located`] is false.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::lit;
+/// use parcomb::parser::*;
+///
+/// let par = lit("ab").and(lit("c"));
+///
+/// let err = par.parse("abd").unwrap_err();
+/// assert!(!err.is_located());
+/// assert_eq!("expected `c`, found `d`", err.to_string());
+///
+/// let err = parse(par, "abd").unwrap_err();
+/// assert!(err.is_located());
+/// assert_eq!("1:3: expected `c`, found `d`", err.to_string());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Offset of the failure from the start of the input, in bytes for text
+    /// and in items for slices.
+    pub offset: usize,
+    /// 1-based line number of the failure, always 1 for slices.
+    pub line: usize,
+    /// 1-based column number of the failure, counted in chars for text and in
+    /// items for slices.
+    pub column: usize,
+    /// The items expected at the failure position, without duplicates and
+    /// in the order they were tried.
+    pub expected: Vec<String>,
+    /// The input fragment found at the failure position, empty at the end of
+    /// the input.
+    pub found: String,
+    /// Whether the failure happened after a `cut`, so that no alternative
+    /// may be tried anymore.
+    pub fatal: bool,
+    /// Length of the input left at the failure position. Unlike `offset`, it
+    /// is known before the error is located, e.g. in a `map_err` closure.
+    pub remaining: usize,
+    /// The rarely set parts of the error, boxed to keep failures cheap.
+    details: Option<Box<Details>>,
+    /// Whether `offset`, `line` and `column` were resolved by `locate`.
+    located: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Details {
+    message: Option<String>,
+    context: Vec<String>,
+}
+
+impl ParseError {
+    /// Creates an error at the start of `input`, expecting `expected`.
+    ///
+    /// # Example:
+    /// ```
+    /// use parcomb::parser::ParseError;
+    ///
+    /// let err = ParseError::new("xyz", "`abc`");
+    /// assert_eq!(vec!["`abc`"], err.expected);
+    /// assert_eq!("x", err.found);
+    /// ```
+    pub fn new(input: &str, expected: impl Into<String>) -> Self {
+        let found = input.chars().next().map(String::from).unwrap_or_default();
+
+        ParseError {
+            offset: 0,
+            line: 1,
+            column: 1,
+            expected: vec![expected.into()],
+            found,
+            fatal: false,
+            remaining: input.len(),
+            details: None,
+            located: false,
+        }
+    }
+
+    /// Whether the position of the error was resolved against the whole
+    /// input, as [`parse`] does. `Display` leaves out the position otherwise.
+    pub fn is_located(&self) -> bool {
+        self.located
+    }
+
+    /// A message replacing `expected`, for failures that are not about a
+    /// missing item, like a failed conversion in `try_map`.
+    pub fn message(&self) -> Option<&str> {
+        self.details.as_ref()?.message.as_deref()
+    }
+
+    /// The constructs being parsed when the failure happened, innermost first.
+    pub fn context(&self) -> &[String] {
+        self.details.as_ref().map_or(&[], |d| &d.context)
+    }
+
+    fn details_mut(&mut self) -> &mut Details {
+        self.details.get_or_insert_with(Default::default)
+    }
+}
+
+/// # Example:
+/// ```
+/// use parcomb::parser::{ParseError, ParserError};
+///
+/// let inp = "[1,\n x]";
+/// let err = ParseError::new(&inp[5..], "number").locate(inp);
+/// assert_eq!((5, 2, 2), (err.offset, err.line, err.column));
+/// ```
+impl ParserError<str> for ParseError {
+    fn locate(mut self, input: &str) -> Self {
+        self.offset = input.len().saturating_sub(self.remaining);
+        (self.line, self.column) = line_col(input, self.offset);
+        self.located = true;
+
+        self
+    }
+
+    fn expected(input: &str, expected: &str) -> Self {
+        ParseError::new(input, expected)
+    }
+
+    fn unexpected(input: &str) -> Self {
+        let mut err = ParseError::new(input, "");
+        err.expected.clear();
+
+        err
+    }
+
+    fn custom(input: &str, message: &str) -> Self {
+        let mut err = Self::unexpected(input);
+        err.details_mut().message = Some(message.to_string());
+
+        err
+    }
+
+    fn merge(mut self, mut other: Self) -> Self {
+        if other.remaining < self.remaining {
+            other.fatal |= self.fatal;
+            return other;
+        }
+
+        self.fatal |= other.fatal;
+        if other.remaining == self.remaining {
+            if self.message().is_none() {
+                if let Some(message) = other.details.and_then(|d| d.message) {
+                    self.details_mut().message = Some(message);
+                }
+            }
+            for exp in other.expected {
+                if !self.expected.contains(&exp) {
+                    self.expected.push(exp);
+                }
+            }
+        }
+
+        self
+    }
+
+    fn is_fatal(&self) -> bool {
+        self.fatal
+    }
+
+    fn into_fatal(mut self) -> Self {
+        self.fatal = true;
+        self
+    }
+
+    fn label(mut self, input: &str, label: &str) -> Self {
+        if self.remaining == input.len() {
+            self.expected = vec![label.to_string()];
+        }
+
+        self
+    }
+
+    fn add_context(mut self, context: &str) -> Self {
+        self.details_mut().context.push(context.to_string());
+        self
+    }
+}
+
+/// Errors on slices: the found item is shown with its `Debug` format, and
+/// the column counts items.
+///
+/// # Example:
+/// ```
+/// use parcomb::parser::{ParseError, ParserError};
+///
+/// let inp: &[u8] = &[1, 2, 3];
+/// let err = ParseError::expected(&inp[2..], "4").locate(inp);
+/// assert_eq!((2, 1, 3), (err.offset, err.line, err.column));
+/// assert_eq!("1:3: expected 4, found `3`", err.to_string());
+/// ```
+impl<T> ParserError<[T]> for ParseError
+where
+    T: fmt::Debug + 'static,
+{
+    fn locate(mut self, input: &[T]) -> Self {
+        self.offset = input.len().saturating_sub(self.remaining);
+        (self.line, self.column) = (1, self.offset + 1);
+        self.located = true;
+
+        self
+    }
+
+    fn expected(input: &[T], expected: &str) -> Self {
+        let found = input.first().map(|t| format!("{t:?}")).unwrap_or_default();
+
+        ParseError {
+            offset: 0,
+            line: 1,
+            column: 1,
+            expected: vec![expected.to_string()],
+            found,
+            fatal: false,
+            remaining: input.len(),
+            details: None,
+            located: false,
+        }
+    }
+
+    fn unexpected(input: &[T]) -> Self {
+        let mut err = Self::expected(input, "");
+        err.expected.clear();
+
+        err
+    }
+
+    fn custom(input: &[T], message: &str) -> Self {
+        let mut err = Self::unexpected(input);
+        err.details_mut().message = Some(message.to_string());
+
+        err
+    }
+
+    fn merge(self, other: Self) -> Self {
+        ParserError::<str>::merge(self, other)
+    }
+
+    fn is_fatal(&self) -> bool {
+        self.fatal
+    }
+
+    fn into_fatal(self) -> Self {
+        ParserError::<str>::into_fatal(self)
+    }
+
+    fn label(mut self, input: &[T], label: &str) -> Self {
+        if self.remaining == input.len() {
+            self.expected = vec![label.to_string()];
+        }
+
+        self
+    }
+
+    fn add_context(self, context: &str) -> Self {
+        ParserError::<str>::add_context(self, context)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.located {
+            write!(f, "{}:{}: ", self.line, self.column)?;
+        }
+
+        if let Some(message) = self.message() {
+            write!(f, "{}", message)?;
+        } else {
+            let found = if self.found.is_empty() {
+                "end of input".to_string()
+            } else {
+                format!("`{}`", self.found)
+            };
+
+            match self.expected.len() {
+                0 => write!(f, "unexpected {}", found)?,
+                1 => write!(f, "expected {}, found {}", self.expected[0], found)?,
+                _ => write!(
+                    f,
+                    "expected one of: {}, found {}",
+                    self.expected.join(", "),
+                    found
+                )?,
+            }
+        }
+
+        for context in self.context() {
+            write!(f, "; {}", context)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The behaviour the combinators need from an error type.
+pub trait ParserError<I>: Sized + 'static
+where
+    I: ?Sized,
+{
+    /// Resolves the position of the error against the whole `input` of a parse.
+    fn locate(self, _input: &I) -> Self {
+        self
+    }
+
+    /// Creates an error at the start of `input`, expecting `expected`.
+    fn expected(input: &I, expected: &str) -> Self;
+
+    /// Creates an error at the start of `input`, expecting nothing in
+    /// particular.
+    fn unexpected(input: &I) -> Self;
+
+    /// Creates an error at the start of `input`, described by `message`.
+    fn custom(input: &I, message: &str) -> Self;
+
+    /// Combines the errors of two failed alternatives: the error that got
+    /// further into the input wins, errors at the same position are merged.
+    fn merge(self, other: Self) -> Self;
+
+    /// Whether the error is fatal. Combinators like `or`, `opt` and `repeat`
+    /// recover from failures, but pass fatal errors through.
+    fn is_fatal(&self) -> bool;
+
+    /// Turns the error into a fatal one.
+    fn into_fatal(self) -> Self;
+
+    /// Replaces the expected items with `label`, if the error happened at the
+    /// start of `input`.
+    fn label(self, input: &I, label: &str) -> Self;
+
+    /// Adds a frame to the chain of constructs being parsed when the error
+    /// happened. Frames are added from the innermost to the outermost.
+    fn add_context(self, context: &str) -> Self;
+}
+
+/// Input types the position of a parser in can be tracked for: text as
+/// `str`, bytes as `[u8]` and token streams as `[T]`.
+///
+/// Offsets are counted in bytes for `str` and in items for slices.
+pub trait Input {
+    /// The items the input is made of, `char` for `str`.
+    type Item<'a>
+    where
+        Self: 'a;
+
+    /// Iterates over the items of the input along with their offsets.
+    type Iter<'a>: Iterator<Item = (usize, Self::Item<'a>)>
+    where
+        Self: 'a;
+
+    /// Returns the length of the input, in the units offsets are counted in.
+    fn input_len(&self) -> usize;
+
+    /// Splits the input in two at the offset `mid`.
+    fn input_split_at(&self, mid: usize) -> (&Self, &Self);
+
+    /// Returns an iterator over the items of the input and their offsets.
+    fn iter_items(&self) -> Self::Iter<'_>;
+
+    /// Returns the offset of `self` in `base`, which `self` must be a part of,
+    /// like the remainder of a parse or a slice of the input it output. The
+    /// default implementation only supports suffixes.
+    ///
+    /// # Example:
+    /// ```
+    /// use parcomb::parser::Input;
+    ///
+    /// let inp = "héllo";
+    /// assert_eq!(3, inp[3..].offset_from(inp));
+    ///
+    /// let toks = [10, 20, 30];
+    /// assert_eq!(2, toks[2..].offset_from(&toks));
+    /// assert_eq!(1, toks[1..2].offset_from(&toks));
+    /// ```
+    fn offset_from(&self, base: &Self) -> usize {
+        base.input_len() - self.input_len()
+    }
+}
+
+impl Input for str {
+    type Item<'a> = char;
+    type Iter<'a> = std::str::CharIndices<'a>;
+
+    fn input_len(&self) -> usize {
+        self.len()
+    }
+
+    fn input_split_at(&self, mid: usize) -> (&Self, &Self) {
+        self.split_at(mid)
+    }
+
+    fn iter_items(&self) -> Self::Iter<'_> {
+        self.char_indices()
+    }
+
+    fn offset_from(&self, base: &Self) -> usize {
+        self.as_ptr() as usize - base.as_ptr() as usize
+    }
+}
+
+impl<T> Input for [T] {
+    type Item<'a>
+        = &'a T
+    where
+        T: 'a;
+    type Iter<'a>
+        = std::iter::Enumerate<std::slice::Iter<'a, T>>
+    where
+        T: 'a;
+
+    fn input_len(&self) -> usize {
+        self.len()
+    }
+
+    fn input_split_at(&self, mid: usize) -> (&Self, &Self) {
+        self.split_at(mid)
+    }
+
+    fn iter_items(&self) -> Self::Iter<'_> {
+        self.iter().enumerate()
+    }
+
+    fn offset_from(&self, base: &Self) -> usize {
+        match std::mem::size_of::<T>() {
+            // all items share an address, only suffixes can be told apart
+            0 => base.len() - self.len(),
+            size => (self.as_ptr() as usize - base.as_ptr() as usize) / size,
+        }
+    }
+}
+
+/// Returns the offset right after the first `n` items of `input`, if it has
+/// that many.
+fn items_end<I>(input: &I, n: usize) -> Option<usize>
+where
+    I: ?Sized + Input,
+{
+    if n == 0 {
+        return Some(0);
+    }
+
+    let mut items = input.iter_items().skip(n - 1);
+    items.next()?;
+
+    Some(items.next().map_or(input.input_len(), |(offset, _)| offset))
+}
+
+/// A range of offsets in the input of a parse, see [`Parser::spanned`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    /// Offset of the first unit covered by the span.
+    pub start: usize,
+    /// Offset right after the last unit covered by the span.
+    pub end: usize,
+}
+
+/// State shared by the parsers of one top-level [`parse`] call.
+#[derive(Default)]
+struct Session {
+    /// Length of the whole input of the parse.
+    len: usize,
+    /// The furthest failure a combinator recovered from so far.
+    furthest: Option<Box<dyn Any>>,
+    /// The context frames of the running parsers, innermost last.
+    context: Vec<String>,
+    /// The results of memoized parsers, by parser id and input offset.
+    memo: HashMap<(MemoKey, usize), Box<dyn Any>>,
+}
+
+thread_local! {
+    /// The sessions of the parses running on this thread, innermost last.
+    static SESSIONS: RefCell<Vec<Session>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Keeps a `Session` open until it is dropped.
+struct SessionGuard;
+
+impl SessionGuard {
+    fn enter(len: usize) -> Self {
+        let session = Session {
+            len,
+            ..Session::default()
+        };
+        SESSIONS.with(|sessions| sessions.borrow_mut().push(session));
+
+        SessionGuard
+    }
+
+    /// Takes the furthest failure recorded in the session, if it is an `E`.
+    fn take_furthest<E: 'static>(&self) -> Option<E> {
+        SESSIONS.with(|sessions| {
+            let furthest = sessions.borrow_mut().last_mut()?.furthest.take()?;
+            furthest.downcast().ok().map(|e| *e)
+        })
+    }
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        SESSIONS.with(|sessions| sessions.borrow_mut().pop());
+    }
+}
+
+/// Keeps a context frame on the innermost `Session` until it is dropped.
+struct ContextGuard {
+    active: bool,
+}
+
+impl ContextGuard {
+    fn enter(context: &str) -> Self {
+        let active = SESSIONS.with(|sessions| match sessions.borrow_mut().last_mut() {
+            Some(session) => {
+                session.context.push(context.to_string());
+                true
+            }
+            None => false,
+        });
+
+        ContextGuard { active }
+    }
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        if self.active {
+            SESSIONS.with(|sessions| {
+                if let Some(session) = sessions.borrow_mut().last_mut() {
+                    session.context.pop();
+                }
+            });
+        }
+    }
+}
+
+/// Records a failure a combinator recovered from, so that [`parse`] can still
+/// report it if the parse fails later at an earlier position.
+///
+/// Does nothing outside of [`parse`].
+fn record_failure<I, E>(e: E)
+where
+    I: ?Sized,
+    E: ParserError<I>,
+{
+    SESSIONS.with(|sessions| {
+        if let Some(session) = sessions.borrow_mut().last_mut() {
+            let e = session
+                .context
+                .iter()
+                .rev()
+                .fold(e, |e, context| e.add_context(context));
+
+            merge_furthest::<I, E>(session, e);
+        }
+    });
+}
+
+/// Merges `e` into the furthest failure of `session`.
+fn merge_furthest<I, E>(session: &mut Session, e: E)
+where
+    I: ?Sized,
+    E: ParserError<I>,
+{
+    let furthest = match session.furthest.take().map(|f| f.downcast::<E>()) {
+        Some(Ok(f)) => f.merge(e),
+        _ => e,
+    };
+    session.furthest = Some(Box::new(furthest));
+}
+
+/// Keeps the failures recorded while it is alive apart from the earlier ones
+/// of the innermost `Session`, so that [`LabelGuard::finish`] can relabel
+/// them before they are merged back.
+struct LabelGuard {
+    earlier: LookaheadGuard,
+}
+
+impl LabelGuard {
+    fn enter() -> Self {
+        LabelGuard {
+            earlier: LookaheadGuard::enter(),
+        }
+    }
+
+    /// Rewrites the failures recorded since `enter` with `f`, and merges them
+    /// with the earlier ones.
+    fn finish<I, E>(self, f: impl FnOnce(E) -> E)
+    where
+        I: ?Sized,
+        E: ParserError<I>,
+    {
+        let recorded = SESSIONS.with(|sessions| {
+            let furthest = sessions.borrow_mut().last_mut()?.furthest.take()?;
+            furthest.downcast::<E>().ok()
+        });
+        drop(self.earlier);
+
+        if let Some(e) = recorded {
+            SESSIONS.with(|sessions| {
+                if let Some(session) = sessions.borrow_mut().last_mut() {
+                    merge_furthest::<I, E>(session, f(*e));
+                }
+            });
+        }
+    }
+}
+
+/// Keeps the failures recorded while it is alive out of the innermost
+/// `Session`, restoring the previous furthest failure when it is dropped.
+///
+/// Lookaheads use it: their failures don't consume input, so they say
+/// nothing about how far the parse got.
+struct LookaheadGuard {
+    furthest: Option<Option<Box<dyn Any>>>,
+}
+
+impl LookaheadGuard {
+    fn enter() -> Self {
+        let furthest =
+            SESSIONS.with(|sessions| Some(sessions.borrow_mut().last_mut()?.furthest.take()));
+
+        LookaheadGuard { furthest }
+    }
+}
+
+impl Drop for LookaheadGuard {
+    fn drop(&mut self) {
+        if let Some(furthest) = self.furthest.take() {
+            SESSIONS.with(|sessions| {
+                if let Some(session) = sessions.borrow_mut().last_mut() {
+                    session.furthest = furthest;
+                }
+            });
+        }
+    }
+}
+
+/// Returns the length of the whole input of the innermost session, if any.
+fn session_len() -> Option<usize> {
+    SESSIONS.with(|sessions| sessions.borrow().last().map(|s| s.len))
+}
+
+/// Identifies a memoizing parser in the memo table of a `Session`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MemoKey {
+    /// A `MemoParser`, by its rule name.
+    Memo(&'static str),
+    /// A `LeftRecParser`, by its rule name.
+    LeftRec(&'static str),
+}
+
+/// A memoized parse result, with the length of the input left on success.
+type Memo<O, E> = Result<(O, usize), E>;
+
+/// Returns the memoized value at `key` in the innermost session, if it is a `T`.
+fn memo_get<T: Clone + 'static>(key: (MemoKey, usize)) -> Option<T> {
+    SESSIONS.with(|sessions| {
+        let sessions = sessions.borrow();
+        sessions.last()?.memo.get(&key)?.downcast_ref().cloned()
+    })
+}
+
+/// Memoizes `value` at `key` in the innermost session, if any.
+fn memo_insert<T: 'static>(key: (MemoKey, usize), value: T) {
+    SESSIONS.with(|sessions| {
+        if let Some(session) = sessions.borrow_mut().last_mut() {
+            session.memo.insert(key, Box::new(value));
+        }
+    });
+}
+
+/// Returns the 1-based line and column (in chars) of a byte `offset` in `input`.
+///
+/// Panics if `offset` is not on a char boundary of `input`.
+///
+/// # Example:
+/// ```
+/// use parcomb::parser::line_col;
+///
+/// let inp = "ab\ncdé\nf";
+/// assert_eq!((1, 1), line_col(inp, 0));
+/// assert_eq!((2, 2), line_col(inp, 4));
+/// assert_eq!((3, 1), line_col(inp, 8));
+/// ```
+pub fn line_col(input: &str, offset: usize) -> (usize, usize) {
+    let before = &input[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let column = before[line_start..].chars().count() + 1;
+
+    (line, column)
+}
 
 /// The common `Parser` trait.
-pub trait Parser<I, O, E>
+///
+/// The trait is object safe: the combinator methods all require
+/// `Self: Sized`, so `dyn Parser<'a, I, O, E>` only exposes `parse`. See
+/// [`BoxedParser`] for an owned, type-erased parser.
+///
+/// The lifetime `'a` is the one of the input, so that outputs can borrow from
+/// it.
+pub trait Parser<'a, I, O, E>
+where
+    I: ?Sized,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E>;
+
+    fn rc(self) -> RcParser<Self>
+    where
+        Self: Sized,
+    {
+        RcParser::new(self)
+    }
+
+    /// Like `rc`, but the parser can be shared between threads if it is
+    /// `Send + Sync`.
+    fn arc(self) -> ArcParser<Self>
+    where
+        Self: Sized,
+    {
+        ArcParser::new(self)
+    }
+
+    fn map<F, O1>(self, f: F) -> MapParser<Self, F, O>
+    where
+        Self: Sized,
+        F: Fn(O) -> O1,
+    {
+        MapParser {
+            parser: self,
+            mapper: f,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn try_map<F, O1, M>(self, f: F) -> TryMapParser<Self, F, O>
+    where
+        Self: Sized,
+        F: Fn(O) -> Result<O1, M>,
+        M: fmt::Display,
+    {
+        TryMapParser {
+            parser: self,
+            mapper: f,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn flat_map<F, P2, O2>(self, f: F) -> FlatMapParser<Self, F, O>
+    where
+        Self: Sized,
+        F: Fn(O) -> P2,
+        P2: Parser<'a, I, O2, E>,
+    {
+        FlatMapParser {
+            parser: self,
+            binder: f,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn map_err<F, E1>(self, f: F) -> MapErrParser<Self, F, E>
+    where
+        Self: Sized,
+        F: Fn(E) -> E1,
+    {
+        MapErrParser {
+            parser: self,
+            mapper: f,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn and<P2, O2>(self, p2: P2) -> AndParser<Self, P2>
+    where
+        Self: Sized,
+        P2: Parser<'a, I, O2, E>,
+    {
+        AndParser { p1: self, p2 }
+    }
+
+    fn and_l<P2, O2>(self, p2: P2) -> AndLeftParser<Self, P2, O2>
+    where
+        Self: Sized,
+        P2: Parser<'a, I, O2, E>,
+    {
+        AndLeftParser {
+            parser: AndParser { p1: self, p2 },
+            _phantom: PhantomData,
+        }
+    }
+
+    fn and_r<P2, O2>(self, p2: P2) -> AndRightParser<Self, P2, O>
+    where
+        Self: Sized,
+        P2: Parser<'a, I, O2, E>,
+    {
+        AndRightParser {
+            parser: AndParser { p1: self, p2 },
+            _phantom: PhantomData,
+        }
+    }
+
+    fn or<P2, O2>(self, p2: P2) -> OrParser<Self, P2>
+    where
+        Self: Sized,
+        P2: Parser<'a, I, O2, E>,
+    {
+        OrParser { p1: self, p2 }
+    }
+
+    fn repeat(self) -> RepeatParser<Self>
+    where
+        Self: Sized,
+    {
+        RepeatParser { parser: self }
+    }
+
+    fn repeat1(self) -> RepeatOneParser<Self>
+    where
+        Self: Sized,
+    {
+        RepeatOneParser { parser: self }
+    }
+
+    /// Runs the parser as many times as possible, at least `range.start` and
+    /// at most `range.end` times. Fails if the minimum isn't met, with a
+    /// message giving the number of repetitions unless the last one failed
+    /// past its start.
+    ///
+    /// # Example:
+    /// ```
+    /// use parcomb::string_parser::reg;
+    /// use parcomb::parser::*;
+    ///
+    /// let octet = reg(r"\d").repeat_range(1..=3);
+    ///
+    /// let (digits, rest) = octet.parse("1234").unwrap();
+    /// assert_eq!(("123", "4"), (digits.concat().as_str(), rest));
+    ///
+    /// let err = parse(octet, ".1").unwrap_err();
+    /// assert_eq!("1:1: expected at least 1 repetition, found 0", err.to_string());
+    /// assert_eq!(vec![r"/\d/"], err.expected);
+    /// ```
+    fn repeat_range<R>(self, range: R) -> RepeatRangeParser<Self>
+    where
+        Self: Sized,
+        R: RangeBounds<usize>,
+    {
+        RepeatRangeParser::new(self, range)
+    }
+
+    /// Runs the parser exactly `n` times.
+    fn repeat_n(self, n: usize) -> RepeatRangeParser<Self>
+    where
+        Self: Sized,
+    {
+        RepeatRangeParser::new(self, n..=n)
+    }
+
+    fn opt(self) -> OptionParser<Self>
+    where
+        Self: Sized,
+    {
+        OptionParser { parser: self }
+    }
+
+    fn skip(self) -> SkipParser<Self, O>
+    where
+        Self: Sized,
+    {
+        SkipParser {
+            parser: self,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn cut(self) -> CutParser<Self>
+    where
+        Self: Sized,
+    {
+        CutParser { parser: self }
+    }
+
+    fn label(self, label: &str) -> LabelParser<Self>
+    where
+        Self: Sized,
+    {
+        LabelParser {
+            parser: self,
+            label: label.to_string(),
+        }
+    }
+
+    fn context(self, context: &str) -> ContextParser<Self>
+    where
+        Self: Sized,
+    {
+        ContextParser {
+            parser: self,
+            context: context.to_string(),
+        }
+    }
+
+    fn spanned(self) -> SpannedParser<Self>
+    where
+        Self: Sized,
+    {
+        SpannedParser { parser: self }
+    }
+
+    fn recognize(self) -> RecognizeParser<Self, O>
+    where
+        Self: Sized,
+    {
+        RecognizeParser {
+            parser: self,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn with_consumed(self) -> WithConsumedParser<Self>
+    where
+        Self: Sized,
+    {
+        WithConsumedParser { parser: self }
+    }
+
+    /// Like [`BoxParser::boxed`], for parsers whose output borrows from the
+    /// input, like `lit_str`. The boxed parser is tied to inputs of the
+    /// lifetime `'a`, so it can't outlive them.
+    ///
+    /// # Example:
+    /// ```
+    /// use parcomb::string_parser::lit_str;
+    /// use parcomb::parser::*;
+    ///
+    /// let inp = String::from("let x");
+    /// let kw: BoxedRefParser<str, &str, ParseError> = lit_str("let").boxed_ref();
+    /// assert_eq!(Ok(("let", " x")), kw.parse(&inp));
+    /// ```
+    fn boxed_ref(self) -> BoxedRefParser<'a, I, O, E>
+    where
+        Self: Sized + 'a,
+    {
+        BoxedRefParser::new(self)
+    }
+
+    fn memo(self, rule: &'static str) -> MemoParser<Self>
+    where
+        Self: Sized,
+    {
+        MemoParser { parser: self, rule }
+    }
+
+    fn memo_slice(self, rule: &'static str) -> MemoSliceParser<Self>
+    where
+        Self: Sized,
+    {
+        MemoSliceParser { parser: self, rule }
+    }
+
+    fn left_rec(self, rule: &'static str) -> LeftRecParser<Self>
+    where
+        Self: Sized,
+    {
+        LeftRecParser { parser: self, rule }
+    }
+}
+
+/// Runs `parser` on a complete `input`, resolving error positions against it.
+///
+/// Failures that combinators like `repeat` or `opt` recovered from are kept,
+/// and the furthest of them is reported if the parse fails at an earlier
+/// position.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::lit;
+/// use parcomb::parser::*;
+///
+/// let err = parse(lit("a").and(lit("b")), "a\nc").unwrap_err();
+/// assert_eq!((1, 1, 2), (err.offset, err.line, err.column));
+///
+/// let err = parse(lit("a\n").and(lit("b")), "a\nc").unwrap_err();
+/// assert_eq!((2, 2, 1), (err.offset, err.line, err.column));
+/// assert_eq!("2:1: expected `b`, found `c`", err.to_string());
+///
+/// let par = lit("[").and(lit("a").repeat()).and(lit("]"));
+/// let err = parse(par, "[aab]").unwrap_err();
+/// assert_eq!(vec!["`a`", "`]`"], err.expected);
+/// ```
+pub fn parse<'a, P, I, O, E>(parser: P, input: &'a I) -> ParseResult<&'a I, O, E>
+where
+    I: ?Sized + Input,
+    P: Parser<'a, I, O, E>,
+    E: ParserError<I>,
+{
+    let session = SessionGuard::enter(input.input_len());
+
+    parser.parse(input).map_err(|e| {
+        let e = match session.take_furthest::<E>() {
+            Some(furthest) => furthest.merge(e),
+            None => e,
+        };
+
+        e.locate(input)
+    })
+}
+
+/// Implements `Parser` trait for functions & closures.
+impl<'a, I, O, E, F> Parser<'a, I, O, E> for F
+where
+    I: ?Sized + 'a,
+    F: Fn(&'a I) -> ParseResult<&'a I, O, E>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        self(input)
+    }
+}
+
+pub struct MapParser<P, F, O> {
+    parser: P,
+    mapper: F,
+    _phantom: PhantomData<fn() -> O>,
+}
+
+impl<'a, I, O1, E, P, F, O> Parser<'a, I, O1, E> for MapParser<P, F, O>
+where
+    I: ?Sized,
+    P: Parser<'a, I, O, E>,
+    F: Fn(O) -> O1,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O1, E> {
+        let (o, i) = self.parser.parse(input)?;
+        let o2 = (self.mapper)(o);
+
+        Ok((o2, i))
+    }
+}
+
+pub struct TryMapParser<P, F, O> {
+    parser: P,
+    mapper: F,
+    _phantom: PhantomData<fn() -> O>,
+}
+
+/// Maps the output with a fallible function. A conversion error fails the
+/// parse at the start of the converted output, with the error as message.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::reg;
+/// use parcomb::parser::*;
+///
+/// let par = reg(r"\d+").try_map(|s| s.parse::<u8>());
+///
+/// let inp = "255";
+/// assert_eq!((255, ""), par.parse(inp).unwrap());
+///
+/// let inp2 = "256";
+/// let err = par.parse(inp2).unwrap_err();
+/// assert_eq!(Some("number too large to fit in target type"), err.message());
+/// ```
+impl<'a, I, O1, E, P, F, O, M> Parser<'a, I, O1, E> for TryMapParser<P, F, O>
+where
+    I: ?Sized,
+    P: Parser<'a, I, O, E>,
+    F: Fn(O) -> Result<O1, M>,
+    M: fmt::Display,
+    E: ParserError<I>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O1, E> {
+        let (o, i) = self.parser.parse(input)?;
+
+        match (self.mapper)(o) {
+            Ok(o1) => Ok((o1, i)),
+            Err(m) => Err(E::custom(input, &m.to_string())),
+        }
+    }
+}
+
+pub struct FlatMapParser<P, F, O> {
+    parser: P,
+    binder: F,
+    _phantom: PhantomData<fn() -> O>,
+}
+
+/// Chooses the parser for the rest of the input from the output of the
+/// wrapped parser.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::{lit, reg};
+/// use parcomb::parser::*;
+///
+/// // a length-prefixed field: `<len>:<data>`
+/// let len = reg(r"\d+").try_map(|s| s.parse::<usize>());
+/// let par = len.and_l(lit(":")).flat_map(|n| reg(&format!("(?s).{{{n}}}")));
+///
+/// let inp = "3:a:bcd";
+/// assert_eq!(("a:b".to_string(), "cd"), par.parse(inp).unwrap());
+///
+/// let inp2 = "5:abc";
+/// assert!(par.parse(inp2).is_err());
+/// ```
+impl<'a, I, O2, E, P, F, O, P2> Parser<'a, I, O2, E> for FlatMapParser<P, F, O>
+where
+    I: ?Sized,
+    P: Parser<'a, I, O, E>,
+    F: Fn(O) -> P2,
+    P2: Parser<'a, I, O2, E>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O2, E> {
+        let (o, i) = self.parser.parse(input)?;
+
+        (self.binder)(o).parse(i)
+    }
+}
+
+pub struct MapErrParser<P, F, E> {
+    parser: P,
+    mapper: F,
+    _phantom: PhantomData<fn() -> E>,
+}
+
+/// Converts the error of the wrapped parser, e.g. to embed a sub-grammar with
+/// its own error type.
+///
+/// The error isn't located yet when it is converted, only [`parse`] resolves
+/// positions against the whole input. Keep `remaining` to locate the
+/// converted error later.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::lit;
+/// use parcomb::parser::*;
+///
+/// let par = lit("ab").and(lit("c")).map_err(|e| (e.remaining, e.expected.join("")));
+///
+/// let inp = "abx";
+/// let (remaining, expected) = par.parse(inp).unwrap_err();
+/// assert_eq!((2, "`c`"), (inp.len() - remaining, expected.as_str()));
+/// ```
+impl<'a, I, O, E1, E, P, F> Parser<'a, I, O, E1> for MapErrParser<P, F, E>
+where
+    I: ?Sized,
+    P: Parser<'a, I, O, E>,
+    F: Fn(E) -> E1,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E1> {
+        self.parser.parse(input).map_err(&self.mapper)
+    }
+}
+
+/// For chaining two parsers.
+pub struct AndParser<P1, P2> {
+    p1: P1,
+    p2: P2,
+}
+
+impl<'a, I, O1, O2, E, P1, P2> Parser<'a, I, (O1, O2), E> for AndParser<P1, P2>
+where
+    I: ?Sized,
+    P1: Parser<'a, I, O1, E>,
+    P2: Parser<'a, I, O2, E>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, (O1, O2), E> {
+        let (o1, i1) = self.p1.parse(input)?;
+        let (o2, i2) = self.p2.parse(i1)?;
+
+        Ok(((o1, o2), i2))
+    }
+}
+
+pub struct AndLeftParser<P1, P2, O2> {
+    parser: AndParser<P1, P2>,
+    _phantom: PhantomData<fn() -> O2>,
+}
+
+/// # Example:
+/// ```
+/// use parcomb::string_parser::lit;
+/// use parcomb::parser::*;
+///
+/// let par = lit("abc").and_l(lit("def"));
+///
+/// let inp = "abcdefg";
+/// let res = par.parse(inp).unwrap();
+/// assert_eq!(("abc".to_string(), "g"), res);
+///
+/// let inp2 = "xxxx";
+/// assert!(par.parse(inp2).is_err());
+/// ```
+impl<'a, I, O1, O2, E, P1, P2> Parser<'a, I, O1, E> for AndLeftParser<P1, P2, O2>
+where
+    I: ?Sized,
+    P1: Parser<'a, I, O1, E>,
+    P2: Parser<'a, I, O2, E>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O1, E> {
+        self.parser.parse(input).map(|((o1, _), i)| (o1, i))
+    }
+}
+
+pub struct AndRightParser<P1, P2, O1> {
+    parser: AndParser<P1, P2>,
+    _phantom: PhantomData<fn() -> O1>,
+}
+
+/// # Example:
+/// ```
+/// use parcomb::string_parser::lit;
+/// use parcomb::parser::*;
+///
+/// let par = lit("abc").and_r(lit("def"));
+///
+/// let inp = "abcdefg";
+/// let res = par.parse(inp).unwrap();
+/// assert_eq!(("def".to_string(), "g"), res);
+///
+/// let inp2 = "xxxx";
+/// assert!(par.parse(inp2).is_err());
+/// ```
+impl<'a, I, O1, O2, E, P1, P2> Parser<'a, I, O2, E> for AndRightParser<P1, P2, O1>
+where
+    I: ?Sized,
+    P1: Parser<'a, I, O1, E>,
+    P2: Parser<'a, I, O2, E>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O2, E> {
+        self.parser.parse(input).map(|((_, o2), i)| (o2, i))
+    }
+}
+
+pub struct OrParser<P1, P2> {
+    p1: P1,
+    p2: P2,
+}
+
+/// # Example:
+/// ```
+/// use parcomb::string_parser::lit;
+/// use parcomb::parser::*;
+///
+/// let par = lit("null").or(lit("true")).or(lit("false"));
+///
+/// let inp = "true";
+/// assert_eq!(("true".to_string(), ""), par.parse(inp).unwrap());
+///
+/// let inp2 = "xxxx";
+/// let err = par.parse(inp2).unwrap_err();
+/// assert_eq!(vec!["`null`", "`true`", "`false`"], err.expected);
+/// ```
+impl<'a, I, O, E, P1, P2> Parser<'a, I, O, E> for OrParser<P1, P2>
+where
+    I: ?Sized,
+    P1: Parser<'a, I, O, E>,
+    P2: Parser<'a, I, O, E>,
+    E: ParserError<I>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        match self.p1.parse(input) {
+            Ok(r) => Ok(r),
+            Err(e1) if e1.is_fatal() => Err(e1),
+            Err(e1) => match self.p2.parse(input) {
+                Ok(r) => {
+                    record_failure::<I, E>(e1);
+                    Ok(r)
+                }
+                Err(e2) => Err(e1.merge(e2)),
+            },
+        }
+    }
+}
+
+/// A tuple of parsers run one after the other, see [`seq`].
+pub trait Sequence<'a, I, O, E>
+where
+    I: ?Sized,
+{
+    fn parse_seq(&self, input: &'a I) -> ParseResult<&'a I, O, E>;
+}
+
+/// A tuple of parsers tried in order, see [`alt`].
+pub trait Alternatives<'a, I, O, E>
+where
+    I: ?Sized,
+{
+    fn parse_alt(&self, input: &'a I) -> ParseResult<&'a I, O, E>;
+}
+
+macro_rules! tuple_impls {
+    ($($P:ident $O:ident $p:ident $o:ident),+) => {
+        impl<'a, I, E, $($P, $O),+> Sequence<'a, I, ($($O,)+), E> for ($($P,)+)
+        where
+            I: ?Sized,
+            $($P: Parser<'a, I, $O, E>),+
+        {
+            fn parse_seq(&self, input: &'a I) -> ParseResult<&'a I, ($($O,)+), E> {
+                let ($($p,)+) = self;
+                $(let ($o, input) = $p.parse(input)?;)+
+
+                Ok((($($o,)+), input))
+            }
+        }
+
+        impl<'a, I, O, E, $($P),+> Alternatives<'a, I, O, E> for ($($P,)+)
+        where
+            I: ?Sized,
+            E: ParserError<I>,
+            $($P: Parser<'a, I, O, E>),+
+        {
+            fn parse_alt(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+                let ($($p,)+) = self;
+                let mut error: Option<E> = None;
+                $(
+                    match $p.parse(input) {
+                        Ok(r) => {
+                            if let Some(e) = error {
+                                record_failure::<I, E>(e);
+                            }
+                            return Ok(r);
+                        }
+                        Err(e) if e.is_fatal() => return Err(e),
+                        Err(e) => {
+                            error = Some(match error {
+                                Some(error) => error.merge(e),
+                                None => e,
+                            });
+                        }
+                    }
+                )+
+
+                Err(error.unwrap())
+            }
+        }
+    };
+}
+
+tuple_impls!(P1 O1 p1 o1);
+tuple_impls!(P1 O1 p1 o1, P2 O2 p2 o2);
+tuple_impls!(P1 O1 p1 o1, P2 O2 p2 o2, P3 O3 p3 o3);
+tuple_impls!(P1 O1 p1 o1, P2 O2 p2 o2, P3 O3 p3 o3, P4 O4 p4 o4);
+tuple_impls!(P1 O1 p1 o1, P2 O2 p2 o2, P3 O3 p3 o3, P4 O4 p4 o4, P5 O5 p5 o5);
+tuple_impls!(P1 O1 p1 o1, P2 O2 p2 o2, P3 O3 p3 o3, P4 O4 p4 o4, P5 O5 p5 o5, P6 O6 p6 o6);
+tuple_impls!(
+    P1 O1 p1 o1, P2 O2 p2 o2, P3 O3 p3 o3, P4 O4 p4 o4, P5 O5 p5 o5, P6 O6 p6 o6,
+    P7 O7 p7 o7
+);
+tuple_impls!(
+    P1 O1 p1 o1, P2 O2 p2 o2, P3 O3 p3 o3, P4 O4 p4 o4, P5 O5 p5 o5, P6 O6 p6 o6,
+    P7 O7 p7 o7, P8 O8 p8 o8
+);
+tuple_impls!(
+    P1 O1 p1 o1, P2 O2 p2 o2, P3 O3 p3 o3, P4 O4 p4 o4, P5 O5 p5 o5, P6 O6 p6 o6,
+    P7 O7 p7 o7, P8 O8 p8 o8, P9 O9 p9 o9
+);
+tuple_impls!(
+    P1 O1 p1 o1, P2 O2 p2 o2, P3 O3 p3 o3, P4 O4 p4 o4, P5 O5 p5 o5, P6 O6 p6 o6,
+    P7 O7 p7 o7, P8 O8 p8 o8, P9 O9 p9 o9, P10 O10 p10 o10
+);
+tuple_impls!(
+    P1 O1 p1 o1, P2 O2 p2 o2, P3 O3 p3 o3, P4 O4 p4 o4, P5 O5 p5 o5, P6 O6 p6 o6,
+    P7 O7 p7 o7, P8 O8 p8 o8, P9 O9 p9 o9, P10 O10 p10 o10, P11 O11 p11 o11
+);
+tuple_impls!(
+    P1 O1 p1 o1, P2 O2 p2 o2, P3 O3 p3 o3, P4 O4 p4 o4, P5 O5 p5 o5, P6 O6 p6 o6,
+    P7 O7 p7 o7, P8 O8 p8 o8, P9 O9 p9 o9, P10 O10 p10 o10, P11 O11 p11 o11,
+    P12 O12 p12 o12
+);
+
+pub struct SeqParser<T> {
+    parsers: T,
+}
+
+impl<'a, I, O, E, T> Parser<'a, I, O, E> for SeqParser<T>
+where
+    I: ?Sized,
+    T: Sequence<'a, I, O, E>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        self.parsers.parse_seq(input)
+    }
+}
+
+/// Runs a tuple of up to 12 parsers in sequence, and returns their outputs as
+/// a flat tuple.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::{lit, reg};
+/// use parcomb::parser::*;
+///
+/// let par = seq((reg(r"\w+"), lit("="), reg(r"\d+")));
+///
+/// let ((key, _, value), rest) = par.parse("x=42;").unwrap();
+/// assert_eq!(("x", "42", ";"), (key.as_str(), value.as_str(), rest));
+/// ```
+pub fn seq<T>(parsers: T) -> SeqParser<T> {
+    SeqParser { parsers }
+}
+
+pub struct AltParser<T> {
+    parsers: T,
+}
+
+impl<'a, I, O, E, T> Parser<'a, I, O, E> for AltParser<T>
+where
+    I: ?Sized,
+    T: Alternatives<'a, I, O, E>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        self.parsers.parse_alt(input)
+    }
+}
+
+/// Tries a tuple of up to 12 parsers in order, and returns the output of the
+/// first one that succeeds. Like `or`, fatal errors are not recovered from.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::lit;
+/// use parcomb::parser::*;
+///
+/// let par = alt((lit("null"), lit("true"), lit("false")));
+///
+/// assert_eq!(("true".to_string(), ""), par.parse("true").unwrap());
+///
+/// let err = par.parse("xxxx").unwrap_err();
+/// assert_eq!(vec!["`null`", "`true`", "`false`"], err.expected);
+/// ```
+pub fn alt<T>(parsers: T) -> AltParser<T> {
+    AltParser { parsers }
+}
+
+pub struct RepeatParser<P> {
+    parser: P,
+}
+
+impl<'a, I, O, E, P> Parser<'a, I, Vec<O>, E> for RepeatParser<P>
+where
+    I: ?Sized,
+    P: Parser<'a, I, O, E>,
+    E: ParserError<I>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, Vec<O>, E> {
+        let mut res: Vec<O> = Vec::new();
+
+        let mut i = input;
+        loop {
+            match self.parser.parse(i) {
+                Err(e) if e.is_fatal() => {
+                    return Err(e);
+                }
+
+                Err(e) => {
+                    record_failure::<I, E>(e);
+                    break;
+                }
+
+                Ok((o, i1)) => {
+                    res.push(o);
+                    i = i1;
+                }
+            }
+        }
+
+        Ok((res, i))
+    }
+}
+
+pub struct RepeatOneParser<P> {
+    parser: P,
+}
+
+impl<'a, I, O, E, P> Parser<'a, I, Vec<O>, E> for RepeatOneParser<P>
+where
+    I: ?Sized,
+    P: Parser<'a, I, O, E>,
+    E: ParserError<I>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, Vec<O>, E> {
+        let mut res: Vec<O> = Vec::new();
+
+        let mut i = input;
+        let e: E;
+        loop {
+            match self.parser.parse(i) {
+                Err(e0) if e0.is_fatal() => {
+                    return Err(e0);
+                }
+
+                Err(e0) => {
+                    e = e0;
+                    break;
+                }
+
+                Ok((o, i1)) => {
+                    res.push(o);
+                    i = i1;
+                }
+            }
+        }
+
+        if !res.is_empty() {
+            record_failure::<I, E>(e);
+            return Ok((res, i));
+        }
+
+        Err(e)
+    }
+}
+
+pub struct RepeatRangeParser<P> {
+    parser: P,
+    min: usize,
+    max: Option<usize>,
+}
+
+impl<P> RepeatRangeParser<P> {
+    fn new<R>(parser: P, range: R) -> Self
+    where
+        R: RangeBounds<usize>,
+    {
+        let min = match range.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => n + 1,
+            Bound::Unbounded => 0,
+        };
+        let max = match range.end_bound() {
+            Bound::Included(&n) => Some(n),
+            Bound::Excluded(&n) => Some(n.checked_sub(1).expect("empty repetition range")),
+            Bound::Unbounded => None,
+        };
+        assert!(max.map_or(true, |max| min <= max), "empty repetition range");
+
+        Self { parser, min, max }
+    }
+
+    fn describe(&self, found: usize) -> String {
+        let plural = |n| if n == 1 { "" } else { "s" };
+        let min = self.min;
+
+        if self.max == Some(min) {
+            format!("expected {min} repetition{}, found {found}", plural(min))
+        } else {
+            format!(
+                "expected at least {min} repetition{}, found {found}",
+                plural(min)
+            )
+        }
+    }
+}
+
+impl<'a, I, O, E, P> Parser<'a, I, Vec<O>, E> for RepeatRangeParser<P>
+where
+    I: ?Sized,
+    P: Parser<'a, I, O, E>,
+    E: ParserError<I>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, Vec<O>, E> {
+        let mut res: Vec<O> = Vec::with_capacity(self.min);
+
+        let mut i = input;
+        while self.max.map_or(true, |max| res.len() < max) {
+            match self.parser.parse(i) {
+                Err(e) if e.is_fatal() => {
+                    return Err(e);
+                }
+
+                Err(e) if res.len() < self.min => {
+                    return Err(e.merge(E::custom(i, &self.describe(res.len()))));
+                }
+
+                Err(e) => {
+                    record_failure::<I, E>(e);
+                    break;
+                }
+
+                Ok((o, i1)) => {
+                    res.push(o);
+                    i = i1;
+                }
+            }
+        }
+
+        Ok((res, i))
+    }
+}
+
+/// Runs `parser` exactly `n` times, the same as `parser.repeat_n(n)`.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::{lit, reg};
+/// use parcomb::parser::*;
+///
+/// let par = lit("\\u").and_r(count(reg("[0-9a-fA-F]"), 4));
+///
+/// let (hex, rest) = par.parse(r"\u2192the").unwrap();
+/// assert_eq!(("2192", "the"), (hex.concat().as_str(), rest));
+///
+/// let err = parse(par, r"\u21").unwrap_err();
+/// assert_eq!("1:5: expected 4 repetitions, found 2", err.to_string());
+/// ```
+pub fn count<'a, P, I, O, E>(parser: P, n: usize) -> RepeatRangeParser<P>
+where
+    I: ?Sized,
+    P: Parser<'a, I, O, E>,
+{
+    parser.repeat_n(n)
+}
+
+pub struct OptionParser<P> {
+    parser: P,
+}
+
+/// # Example:
+/// ```
+/// use parcomb::string_parser::lit;
+/// use parcomb::parser::*;
+///
+/// let par = lit("abc").opt();
+///
+/// let inp = "abcd";
+/// let res = par.parse(inp).unwrap();
+/// assert_eq!((Some("abc".to_string()), "d"), res);
+///
+/// let inp2 = "xxxx";
+/// let res = par.parse(inp2).unwrap();
+/// assert_eq!((None, "xxxx"), res);
+/// ```
+impl<'a, I, O, E, P> Parser<'a, I, Option<O>, E> for OptionParser<P>
+where
+    I: ?Sized,
+    P: Parser<'a, I, O, E>,
+    E: ParserError<I>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, Option<O>, E> {
+        match self.parser.parse(input) {
+            Ok((r, i)) => Ok((Some(r), i)),
+            Err(e) if e.is_fatal() => Err(e),
+            Err(e) => {
+                record_failure::<I, E>(e);
+                Ok((None, input))
+            }
+        }
+    }
+}
+
+pub struct SkipParser<P, O> {
+    parser: P,
+    _phantom: PhantomData<fn() -> O>,
+}
+
+/// # Example:
+/// ```
+/// use parcomb::string_parser::lit;
+/// use parcomb::parser::*;
+///
+/// let par = lit("abc").skip();
+///
+/// let inp = "abcd";
+/// let res = par.parse(inp).unwrap();
+/// assert_eq!(((), "d"), res);
+///
+/// let inp2 = "xxxx";
+/// assert!(par.parse(inp2).is_err());
+/// ```
+impl<'a, I, O, E, P> Parser<'a, I, (), E> for SkipParser<P, O>
+where
+    I: ?Sized,
+    P: Parser<'a, I, O, E>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, (), E> {
+        self.parser.parse(input).map(|(_, i)| ((), i))
+    }
+}
+
+pub struct CutParser<P> {
+    parser: P,
+}
+
+/// Makes the failures of the wrapped parser fatal, so that `or`, `opt`,
+/// `repeat` and `lst_sep` stop backtracking once the parser was reached.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::{lit, reg};
+/// use parcomb::parser::*;
+///
+/// let arr = lit("[").and_r(reg(r"\d+").and_l(lit("]")).cut());
+/// let par = arr.or(reg(r"\[.*"));
+///
+/// let inp = "[1]";
+/// assert_eq!(("1".to_string(), ""), par.parse(inp).unwrap());
+///
+/// // no fallback to the second branch once `[` matched
+/// let inp2 = "[x]";
+/// let err = par.parse(inp2).unwrap_err();
+/// assert!(err.fatal);
+/// assert_eq!(vec![r"/\d+/"], err.expected);
+/// ```
+impl<'a, I, O, E, P> Parser<'a, I, O, E> for CutParser<P>
+where
+    I: ?Sized,
+    P: Parser<'a, I, O, E>,
+    E: ParserError<I>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        self.parser.parse(input).map_err(|e| e.into_fatal())
+    }
+}
+
+pub struct LabelParser<P> {
+    parser: P,
+    label: String,
+}
+
+/// Names what the wrapped parser expects, in place of what its inner
+/// parsers expect, when it fails at its start. This includes the failures
+/// inner combinators like `opt` recovered from there, that [`parse`] may
+/// report later.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::reg;
+/// use parcomb::parser::*;
+///
+/// let par = reg(r"-?\d+").label("integer");
+///
+/// let inp = "x";
+/// let err = par.parse(inp).unwrap_err();
+/// assert_eq!(vec!["integer"], err.expected);
+/// ```
+impl<'a, I, O, E, P> Parser<'a, I, O, E> for LabelParser<P>
+where
+    I: ?Sized,
+    P: Parser<'a, I, O, E>,
+    E: ParserError<I>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        let guard = LabelGuard::enter();
+        let res = self.parser.parse(input);
+        guard.finish::<I, E>(|e| e.label(input, &self.label));
+
+        res.map_err(|e| e.label(input, &self.label))
+    }
+}
+
+pub struct ContextParser<P> {
+    parser: P,
+    context: String,
+}
+
+/// Adds a context frame to the failures of the wrapped parser, including the
+/// ones recovered from and reported later by [`parse`].
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::lit;
+/// use parcomb::parser::*;
+///
+/// let item = lit("a").context("in item");
+/// let par = lit("[").and(item).and(lit("]")).context("in list");
+///
+/// let inp = "[b]";
+/// let err = parse(par, inp).unwrap_err();
+/// assert_eq!(vec!["in item", "in list"], err.context());
+/// assert_eq!("1:2: expected `a`, found `b`; in item; in list", err.to_string());
+/// ```
+impl<'a, I, O, E, P> Parser<'a, I, O, E> for ContextParser<P>
+where
+    I: ?Sized,
+    P: Parser<'a, I, O, E>,
+    E: ParserError<I>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        let _frame = ContextGuard::enter(&self.context);
+
+        self.parser
+            .parse(input)
+            .map_err(|e| e.add_context(&self.context))
+    }
+}
+
+pub struct SpannedParser<P> {
+    parser: P,
+}
+
+/// Pairs the output of the wrapped parser with the `Span` it was parsed from.
+///
+/// Offsets are relative to the input of the top-level [`parse`] function, so
+/// spans are only meaningful under it. Through [`Parser::parse`] alone there
+/// is no whole input to count from: offsets are then relative to the input
+/// the spanned parser itself got, wherever it runs in the grammar.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::{lit, reg};
+/// use parcomb::parser::*;
+///
+/// let par = lit("let ").and_r(reg(r"\w+").spanned());
+///
+/// let inp = "let abc = 1";
+/// let ((name, span), _) = parse(par, inp).unwrap();
+/// assert_eq!("abc", name);
+/// assert_eq!(Span { start: 4, end: 7 }, span);
+/// assert_eq!("abc", &inp[span.start..span.end]);
+///
+/// // without `parse`, the span starts where the spanned parser did
+/// let par = lit("let ").and_r(reg(r"\w+").spanned());
+/// let ((_, span), _) = par.parse(inp).unwrap();
+/// assert_eq!(Span { start: 0, end: 3 }, span);
+/// ```
+impl<'a, I, O, E, P> Parser<'a, I, (O, Span), E> for SpannedParser<P>
+where
+    I: ?Sized + Input,
+    P: Parser<'a, I, O, E>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, (O, Span), E> {
+        let (o, i) = self.parser.parse(input)?;
+
+        let len = session_len().unwrap_or(input.input_len());
+        let span = Span {
+            start: len - input.input_len(),
+            end: len - i.input_len(),
+        };
+
+        Ok(((o, span), i))
+    }
+}
+
+/// Returns the part of `input` consumed by a parser that left `rest`.
+fn consumed<'a, I>(input: &'a I, rest: &'a I) -> &'a I
+where
+    I: ?Sized + Input,
+{
+    input.input_split_at(rest.offset_from(input)).0
+}
+
+pub struct RecognizeParser<P, O> {
+    parser: P,
+    _phantom: PhantomData<fn() -> O>,
+}
+
+/// Discards the output of the wrapped parser, and returns the slice of the
+/// input it consumed instead.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::{lit, reg};
+/// use parcomb::parser::*;
+///
+/// // a qualified identifier, e.g. `a.b.c`
+/// let par = lst_sep(reg(r"\w+"), lit(".")).recognize();
+///
+/// assert_eq!(("std.io.Read", "()"), par.parse("std.io.Read()").unwrap());
+/// ```
+impl<'a, I, O, E, P> Parser<'a, I, &'a I, E> for RecognizeParser<P, O>
+where
+    I: ?Sized + Input,
+    P: Parser<'a, I, O, E>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, &'a I, E> {
+        let (_, i) = self.parser.parse(input)?;
+
+        Ok((consumed(input, i), i))
+    }
+}
+
+pub struct WithConsumedParser<P> {
+    parser: P,
+}
+
+/// Pairs the output of the wrapped parser with the slice of the input it
+/// consumed.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::reg;
+/// use parcomb::parser::*;
+///
+/// let par = reg(r"\d+").map(|s| s.parse::<u32>().unwrap()).with_consumed();
+///
+/// assert_eq!(((42, "042"), "!"), par.parse("042!").unwrap());
+/// ```
+impl<'a, I, O, E, P> Parser<'a, I, (O, &'a I), E> for WithConsumedParser<P>
+where
+    I: ?Sized + Input,
+    P: Parser<'a, I, O, E>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, (O, &'a I), E> {
+        let (o, i) = self.parser.parse(input)?;
+
+        Ok(((o, consumed(input, i)), i))
+    }
+}
+
+/// A parser caching its results by rule name, see [`Parser::memo`].
+pub struct MemoParser<P> {
+    parser: P,
+    rule: &'static str,
+}
+
+/// Caches the results of the wrapped parser by rule name and input offset, so
+/// that the rule runs at most once per offset in a top-level [`parse`] call.
+/// Outside of [`parse`], it parses without caching.
+///
+/// The name identifies the rule, not the parser value: a fn item that builds
+/// its memoized parser on every call still hits the cache. The name must be
+/// unique among the memoized rules of a grammar.
+///
+/// The output must be `'static`, since the cache outlives the parsers: use
+/// [`Parser::memo_slice`] for outputs borrowing from the input.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::{lit, reg};
+/// use parcomb::parser::*;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// static CALLS: AtomicUsize = AtomicUsize::new(0);
+///
+/// fn word(input: &str) -> ParseResult<&str, String> {
+///     let counted = |input| {
+///         CALLS.fetch_add(1, Ordering::Relaxed);
+///         reg(r"\w+").parse(input)
+///     };
+///
+///     counted.memo("word").parse(input)
+/// }
+///
+/// let par = word.and(lit("!")).or(word.and(lit("?")));
+///
+/// let inp = "hello?";
+/// assert!(parse(par, inp).is_ok());
+/// assert_eq!(1, CALLS.load(Ordering::Relaxed));
+/// ```
+impl<'a, I, O, E, P> Parser<'a, I, O, E> for MemoParser<P>
+where
+    I: ?Sized + Input,
+    P: Parser<'a, I, O, E>,
+    O: Clone + 'static,
+    E: ParserError<I> + Clone,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        let Some(len) = session_len() else {
+            return self.parser.parse(input);
+        };
+        let key = (MemoKey::Memo(self.rule), len - input.input_len());
+
+        if let Some(memo) = memo_get::<Memo<O, E>>(key) {
+            return replay_memo(memo, input);
+        }
+
+        let res = self.parser.parse(input);
+        memo_insert(key, to_memo(&res));
+
+        res
+    }
+}
+
+pub struct MemoSliceParser<P> {
+    parser: P,
+    rule: &'static str,
+}
+
+/// Like [`Parser::memo`], for parsers whose output is a slice of their input,
+/// like `lit_str`, `reg_str` or [`Parser::recognize`]. The cache keeps the
+/// span of the output, which is sliced back out of the input on a hit.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::{lit_str, reg_str};
+/// use parcomb::parser::*;
+///
+/// fn word<'a>(input: &'a str) -> ParseResult<&'a str, &'a str> {
+///     reg_str(r"\w+").memo_slice("word").parse(input)
+/// }
+///
+/// let par = word.and(lit_str("!")).or(word.and(lit_str("?")));
+///
+/// let inp = String::from("hello?");
+/// assert_eq!(Ok((("hello", "?"), "")), parse(par, &inp[..]));
+/// ```
+impl<'a, I, E, P> Parser<'a, I, &'a I, E> for MemoSliceParser<P>
+where
+    I: ?Sized + Input,
+    P: Parser<'a, I, &'a I, E>,
+    E: ParserError<I> + Clone,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, &'a I, E> {
+        let Some(len) = session_len() else {
+            return self.parser.parse(input);
+        };
+        let key = (MemoKey::Memo(self.rule), len - input.input_len());
+
+        if let Some(memo) = memo_get::<Memo<Span, E>>(key) {
+            return replay_memo(memo, input).map(|(span, i)| {
+                let (o, _) = input.input_split_at(span.end);
+                (o.input_split_at(span.start).1, i)
+            });
+        }
+
+        let res = self.parser.parse(input);
+        let memo = match &res {
+            Ok((o, i)) => {
+                let start = o.offset_from(input);
+                let end = start + o.input_len();
+                Ok((Span { start, end }, i.input_len()))
+            }
+            Err(e) => Err(e.clone()),
+        };
+        memo_insert(key, memo);
+
+        res
+    }
+}
+
+/// Converts a parse result into its memoized form.
+fn to_memo<I, O, E>(res: &ParseResult<&I, O, E>) -> Memo<O, E>
+where
+    I: ?Sized + Input,
+    O: Clone,
+    E: Clone,
+{
+    match res {
+        Ok((o, i)) => Ok((o.clone(), i.input_len())),
+        Err(e) => Err(e.clone()),
+    }
+}
+
+/// Turns a memoized result back into the result of a parse of `input`.
+fn replay_memo<I, O, E>(memo: Memo<O, E>, input: &I) -> ParseResult<&I, O, E>
+where
+    I: ?Sized + Input,
+{
+    memo.map(|(o, rest)| {
+        let (_, i) = input.input_split_at(input.input_len() - rest);
+        (o, i)
+    })
+}
+
+pub struct LeftRecParser<P> {
+    parser: P,
+    rule: &'static str,
+}
+
+/// Supports left recursion in the wrapped parser, by growing a seed: the
+/// recursive call first fails, then returns the previous result, for as long
+/// as the result gets longer.
+///
+/// Results are memoized by the rule name, which must be unique among the
+/// left-recursive rules of a grammar. Wrap the body of the rule, and don't
+/// memoize other parsers on the recursion cycle.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::{lit, reg};
+/// use parcomb::parser::*;
+///
+/// // expr = expr "-" num | num
+/// fn expr(input: &str) -> ParseResult<&str, i64> {
+///     let num = || reg(r"\d+").map(|s| s.parse::<i64>().unwrap());
+///     let sub = expr.and_l(lit("-")).and(num()).map(|(x, y)| x - y);
+///
+///     sub.or(num()).left_rec("expr").parse(input)
+/// }
+///
+/// let inp = "10-2-3";
+/// assert_eq!((5, ""), parse(expr, inp).unwrap());
+/// ```
+impl<'a, I, O, E, P> Parser<'a, I, O, E> for LeftRecParser<P>
+where
+    I: ?Sized + Input,
+    P: Parser<'a, I, O, E>,
+    O: Clone + 'static,
+    E: ParserError<I> + Clone,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        let _session = match session_len() {
+            Some(_) => None,
+            None => Some(SessionGuard::enter(input.input_len())),
+        };
+        let len = session_len().unwrap_or_default();
+        let key = (MemoKey::LeftRec(self.rule), len - input.input_len());
+
+        if let Some(memo) = memo_get::<Memo<O, E>>(key) {
+            return replay_memo(memo, input);
+        }
+
+        memo_insert::<Memo<O, E>>(key, Err(E::unexpected(input)));
+
+        let mut seed: Option<(O, &'a I)> = None;
+        loop {
+            match (self.parser.parse(input), &seed) {
+                (Ok((_, i)), Some((_, i0))) if i.input_len() >= i0.input_len() => {
+                    break;
+                }
+                (Ok((o, i)), _) => {
+                    memo_insert::<Memo<O, E>>(key, Ok((o.clone(), i.input_len())));
+                    seed = Some((o, i));
+                }
+                (Err(e), None) => {
+                    memo_insert::<Memo<O, E>>(key, Err(e.clone()));
+                    return Err(e);
+                }
+                (Err(e), Some(_)) if e.is_fatal() => {
+                    return Err(e);
+                }
+                (Err(_), Some(_)) => {
+                    break;
+                }
+            }
+        }
+
+        Ok(seed.unwrap())
+    }
+}
+
+pub struct RcParser<P> {
+    parser: Rc<P>,
+}
+
+impl<P> RcParser<P> {
+    pub fn new<'a, I, O, E>(p: P) -> Self
+    where
+        I: ?Sized,
+        P: Parser<'a, I, O, E>,
+    {
+        Self { parser: Rc::new(p) }
+    }
+}
+
+impl<'a, I, O, E, P> Parser<'a, I, O, E> for RcParser<P>
+where
+    I: ?Sized,
+    P: Parser<'a, I, O, E>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        self.parser.parse(input)
+    }
+}
+
+impl<P> Clone for RcParser<P> {
+    fn clone(&self) -> Self {
+        Self {
+            parser: self.parser.clone(),
+        }
+    }
+}
+
+pub struct ArcParser<P> {
+    parser: Arc<P>,
+}
+
+impl<P> ArcParser<P> {
+    pub fn new<'a, I, O, E>(p: P) -> Self
+    where
+        I: ?Sized,
+        P: Parser<'a, I, O, E>,
+    {
+        Self {
+            parser: Arc::new(p),
+        }
+    }
+}
+
+impl<'a, I, O, E, P> Parser<'a, I, O, E> for ArcParser<P>
+where
+    I: ?Sized,
+    P: Parser<'a, I, O, E>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        self.parser.parse(input)
+    }
+}
+
+impl<P> Clone for ArcParser<P> {
+    fn clone(&self) -> Self {
+        Self {
+            parser: self.parser.clone(),
+        }
+    }
+}
+
+/// Methods of parsers that work on inputs of any lifetime, implemented for
+/// all of them.
+pub trait BoxParser<I, O, E>: for<'a> Parser<'a, I, O, E>
+where
+    I: ?Sized,
+{
+    /// Erases the type of the parser, e.g. to store parsers of different
+    /// types in a collection. See [`Parser::boxed_ref`] for parsers whose
+    /// output borrows from the input.
+    ///
+    /// # Example:
+    /// ```
+    /// use parcomb::string_parser::{lit, reg};
+    /// use parcomb::parser::*;
+    ///
+    /// let pars = vec![lit("let").boxed(), reg(r"\d+").boxed()];
+    /// assert_eq!(Ok(("12".to_string(), "")), pars[1].parse("12"));
+    /// ```
+    fn boxed<'p>(self) -> BoxedParser<'p, I, O, E>
+    where
+        Self: Sized + 'p,
+    {
+        BoxedParser::new(self)
+    }
+
+    /// Like `boxed`, but the boxed parser stays `Send + Sync`, e.g. to build
+    /// a grammar at runtime and share it between threads.
+    ///
+    /// # Example:
+    /// ```
+    /// use parcomb::string_parser::lit;
+    /// use parcomb::parser::*;
+    /// use std::thread;
+    ///
+    /// let kw = ["if", "else"].map(|k| lit(k).boxed_sync());
+    /// let kw = kw.into_iter().reduce(|acc, p| acc.or(p).boxed_sync()).unwrap();
+    ///
+    /// let res = thread::scope(|s| s.spawn(|| kw.parse("else")).join().unwrap());
+    /// assert_eq!(Ok(("else".to_string(), "")), res);
+    /// ```
+    fn boxed_sync<'p>(self) -> SyncBoxedParser<'p, I, O, E>
+    where
+        Self: Sized + Send + Sync + 'p,
+    {
+        SyncBoxedParser::new(self)
+    }
+}
+
+impl<I, O, E, P> BoxParser<I, O, E> for P
+where
+    I: ?Sized,
+    P: for<'a> Parser<'a, I, O, E>,
+{
+}
+
+/// A type-erased parser, see [`BoxParser::boxed`]. It is neither `Send` nor
+/// `Sync`, see [`SyncBoxedParser`] for one that can be shared between
+/// threads.
+pub struct BoxedParser<'p, I, O, E>
+where
+    I: ?Sized,
+{
+    parser: Box<dyn for<'a> Parser<'a, I, O, E> + 'p>,
+}
+
+impl<'p, I, O, E> BoxedParser<'p, I, O, E>
+where
+    I: ?Sized,
+{
+    pub fn new<P>(p: P) -> Self
+    where
+        P: for<'a> Parser<'a, I, O, E> + 'p,
+    {
+        Self {
+            parser: Box::new(p),
+        }
+    }
+}
+
+impl<'a, I, O, E> Parser<'a, I, O, E> for BoxedParser<'_, I, O, E>
+where
+    I: ?Sized,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        self.parser.parse(input)
+    }
+}
+
+/// A type-erased parser that is `Send + Sync`, see [`BoxParser::boxed_sync`].
+pub struct SyncBoxedParser<'p, I, O, E>
+where
+    I: ?Sized,
+{
+    parser: Box<dyn for<'a> Parser<'a, I, O, E> + Send + Sync + 'p>,
+}
+
+impl<'p, I, O, E> SyncBoxedParser<'p, I, O, E>
+where
+    I: ?Sized,
+{
+    pub fn new<P>(p: P) -> Self
+    where
+        P: for<'a> Parser<'a, I, O, E> + Send + Sync + 'p,
+    {
+        Self {
+            parser: Box::new(p),
+        }
+    }
+}
+
+impl<'a, I, O, E> Parser<'a, I, O, E> for SyncBoxedParser<'_, I, O, E>
+where
+    I: ?Sized,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        self.parser.parse(input)
+    }
+}
+
+/// A type-erased parser of inputs of the lifetime `'a`, whose output may
+/// borrow from them, see [`Parser::boxed_ref`].
+pub struct BoxedRefParser<'a, I, O, E>
+where
+    I: ?Sized,
+{
+    parser: Box<dyn Parser<'a, I, O, E> + 'a>,
+}
+
+impl<'a, I, O, E> BoxedRefParser<'a, I, O, E>
+where
+    I: ?Sized,
+{
+    pub fn new<P>(p: P) -> Self
+    where
+        P: Parser<'a, I, O, E> + 'a,
+    {
+        Self {
+            parser: Box::new(p),
+        }
+    }
+}
+
+impl<'a, I, O, E> Parser<'a, I, O, E> for BoxedRefParser<'a, I, O, E>
+where
+    I: ?Sized,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        self.parser.parse(input)
+    }
+}
+
+/// The shared cell the definition of a recursive parser is stored in.
+trait RecursiveCell: Clone {
+    type Parser;
+    type Weak: Clone;
+
+    fn new() -> Self;
+    fn get(&self) -> Option<&Self::Parser>;
+    fn set(&self, parser: Self::Parser) -> Result<(), Self::Parser>;
+    fn downgrade(&self) -> Self::Weak;
+    fn upgrade(weak: &Self::Weak) -> Option<Self>;
+}
+
+impl<T> RecursiveCell for Rc<OnceCell<T>> {
+    type Parser = T;
+    type Weak = Weak<OnceCell<T>>;
+
+    fn new() -> Self {
+        Rc::new(OnceCell::new())
+    }
+
+    fn get(&self) -> Option<&T> {
+        OnceCell::get(self)
+    }
+
+    fn set(&self, parser: T) -> Result<(), T> {
+        OnceCell::set(self, parser)
+    }
+
+    fn downgrade(&self) -> Self::Weak {
+        Rc::downgrade(self)
+    }
+
+    fn upgrade(weak: &Self::Weak) -> Option<Self> {
+        weak.upgrade()
+    }
+}
+
+impl<T> RecursiveCell for Arc<OnceLock<T>> {
+    type Parser = T;
+    type Weak = sync::Weak<OnceLock<T>>;
+
+    fn new() -> Self {
+        Arc::new(OnceLock::new())
+    }
+
+    fn get(&self) -> Option<&T> {
+        OnceLock::get(self)
+    }
+
+    fn set(&self, parser: T) -> Result<(), T> {
+        OnceLock::set(self, parser)
+    }
+
+    fn downgrade(&self) -> Self::Weak {
+        Arc::downgrade(self)
+    }
+
+    fn upgrade(weak: &Self::Weak) -> Option<Self> {
+        weak.upgrade()
+    }
+}
+
+/// The handle passed to the builder of a recursive parser only holds a weak
+/// reference, so that the parser doesn't own itself.
+enum RecursiveLink<C>
+where
+    C: RecursiveCell,
+{
+    Strong(C),
+    Weak(C::Weak),
+}
+
+impl<C> RecursiveLink<C>
+where
+    C: RecursiveCell,
+{
+    /// Builds the definition of a recursive parser with `f`, which gets a
+    /// weak handle to the parser being built.
+    fn build(f: impl FnOnce(Self) -> C::Parser) -> Self {
+        let cell = C::new();
+        let parser = f(RecursiveLink::Weak(cell.downgrade()));
+        if cell.set(parser).is_err() {
+            unreachable!("recursive parser built twice");
+        }
+
+        RecursiveLink::Strong(cell)
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&C::Parser) -> R) -> R {
+        let cell = match self {
+            RecursiveLink::Strong(cell) => cell.clone(),
+            RecursiveLink::Weak(cell) => {
+                C::upgrade(cell).expect("recursive parser used after it was dropped")
+            }
+        };
+        let parser = cell
+            .get()
+            .expect("recursive parser used while it is being built");
+
+        f(parser)
+    }
+}
+
+impl<C> Clone for RecursiveLink<C>
+where
+    C: RecursiveCell,
+{
+    fn clone(&self) -> Self {
+        match self {
+            RecursiveLink::Strong(cell) => RecursiveLink::Strong(cell.clone()),
+            RecursiveLink::Weak(cell) => RecursiveLink::Weak(cell.clone()),
+        }
+    }
+}
+
+/// A parser referring to itself, see [`recursive`]. The parser is
+/// reference counted with `Rc`, so it can't be shared between threads, see
+/// [`SyncRecursiveParser`] for one that can.
+pub struct RecursiveParser<'p, I, O, E>
+where
+    I: ?Sized,
+{
+    parser: RecursiveLink<Rc<OnceCell<BoxedParser<'p, I, O, E>>>>,
+}
+
+impl<'a, I, O, E> Parser<'a, I, O, E> for RecursiveParser<'_, I, O, E>
+where
+    I: ?Sized,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        self.parser.with(|parser| parser.parse(input))
+    }
+}
+
+impl<I, O, E> Clone for RecursiveParser<'_, I, O, E>
+where
+    I: ?Sized,
+{
+    fn clone(&self) -> Self {
+        Self {
+            parser: self.parser.clone(),
+        }
+    }
+}
+
+/// Builds a parser that refers to itself: `f` gets a handle to the parser
+/// being built, and returns its definition. See [`recursive_ref`] for
+/// parsers whose output borrows from the input.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::lit;
+/// use parcomb::parser::*;
+///
+/// // nested = "(" nested* ")"
+/// let nested = recursive(|nested| {
+///     lit("(")
+///         .and_r(nested.repeat())
+///         .and_l(lit(")"))
+///         .map(|inner| inner.iter().sum::<usize>() + 1)
+/// });
+///
+/// let inp = "(()(()))";
+/// assert_eq!((4, ""), nested.parse(inp).unwrap());
+/// ```
+pub fn recursive<'p, F, P, I, O, E>(f: F) -> RecursiveParser<'p, I, O, E>
+where
+    I: ?Sized,
+    F: FnOnce(RecursiveParser<'p, I, O, E>) -> P,
+    P: for<'a> Parser<'a, I, O, E> + 'p,
+{
+    let parser = RecursiveLink::build(|parser| f(RecursiveParser { parser }).boxed());
+
+    RecursiveParser { parser }
+}
+
+/// A parser referring to itself that is `Send + Sync`, see
+/// [`recursive_sync`].
+pub struct SyncRecursiveParser<'p, I, O, E>
+where
+    I: ?Sized,
+{
+    parser: RecursiveLink<Arc<OnceLock<SyncBoxedParser<'p, I, O, E>>>>,
+}
+
+impl<'a, I, O, E> Parser<'a, I, O, E> for SyncRecursiveParser<'_, I, O, E>
+where
+    I: ?Sized,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        self.parser.with(|parser| parser.parse(input))
+    }
+}
+
+impl<I, O, E> Clone for SyncRecursiveParser<'_, I, O, E>
+where
+    I: ?Sized,
+{
+    fn clone(&self) -> Self {
+        Self {
+            parser: self.parser.clone(),
+        }
+    }
+}
+
+/// Like [`recursive`], but the parser is reference counted with `Arc`, so
+/// that it is `Send + Sync` if its definition is, e.g. to use it in
+/// [`lazy_parser!`](crate::lazy_parser).
+///
+/// # Example:
+/// ```
+/// use parcomb::lazy_parser;
+/// use parcomb::string_parser::lit;
+/// use parcomb::parser::*;
+///
+/// lazy_parser! {
+///     // nested = "(" nested* ")"
+///     fn nested_p(&str) -> usize = recursive_sync(|nested| {
+///         lit("(")
+///             .and_r(nested.repeat())
+///             .and_l(lit(")"))
+///             .map(|inner| inner.iter().sum::<usize>() + 1)
+///     });
+/// }
+///
+/// assert_eq!(Ok((4, "")), nested_p("(()(()))"));
+/// ```
+pub fn recursive_sync<'p, F, P, I, O, E>(f: F) -> SyncRecursiveParser<'p, I, O, E>
+where
+    I: ?Sized,
+    F: FnOnce(SyncRecursiveParser<'p, I, O, E>) -> P,
+    P: for<'a> Parser<'a, I, O, E> + Send + Sync + 'p,
+{
+    let parser = RecursiveLink::build(|parser| f(SyncRecursiveParser { parser }).boxed_sync());
+
+    SyncRecursiveParser { parser }
+}
+
+/// A parser referring to itself whose output may borrow from inputs of the
+/// lifetime `'a`, see [`recursive_ref`].
+pub struct RecursiveRefParser<'a, I, O, E>
+where
+    I: ?Sized,
+{
+    parser: RecursiveLink<Rc<OnceCell<BoxedRefParser<'a, I, O, E>>>>,
+}
+
+impl<'a, I, O, E> Parser<'a, I, O, E> for RecursiveRefParser<'a, I, O, E>
+where
+    I: ?Sized,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        self.parser.with(|parser| parser.parse(input))
+    }
+}
+
+impl<I, O, E> Clone for RecursiveRefParser<'_, I, O, E>
+where
+    I: ?Sized,
+{
+    fn clone(&self) -> Self {
+        Self {
+            parser: self.parser.clone(),
+        }
+    }
+}
+
+/// Like [`recursive`], for parsers whose output borrows from the input. The
+/// parser is tied to inputs of the lifetime `'a`.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::{lit_str, reg_str};
+/// use parcomb::parser::*;
+///
+/// // words = word ("," words)?
+/// let inp = String::from("a,bc,d;");
+/// let words = recursive_ref(|words| {
+///     reg_str(r"\w+")
+///         .and(lit_str(",").and_r(words).opt())
+///         .map(|(w, rest)| [vec![w], rest.unwrap_or_default()].concat())
+/// });
+///
+/// assert_eq!(Ok((vec!["a", "bc", "d"], ";")), words.parse(&inp));
+/// ```
+pub fn recursive_ref<'a, F, P, I, O, E>(f: F) -> RecursiveRefParser<'a, I, O, E>
+where
+    I: ?Sized,
+    F: FnOnce(RecursiveRefParser<'a, I, O, E>) -> P,
+    P: Parser<'a, I, O, E> + 'a,
+{
+    let parser = RecursiveLink::build(|parser| f(RecursiveRefParser { parser }).boxed_ref());
+
+    RecursiveRefParser { parser }
+}
+
+/// Defines parser functions whose parser is built once, on first use, and
+/// then shared by all parses and threads. Regexes in particular are only
+/// compiled once.
+///
+/// The parser must be `Send + Sync`: build recursive and type-erased parsers
+/// with [`recursive_sync`] and [`BoxParser::boxed_sync`]. The error type
+/// defaults to `ParseError`, another one can be given after the output type.
+/// The output may borrow from the input with the lifetime `'a`, e.g.
+/// `&'a str`.
+///
+/// # Example:
+/// ```
+/// use parcomb::lazy_parser;
+/// use parcomb::parser::*;
+/// use parcomb::string_parser::{lit, lit_str, reg, reg_str};
+///
+/// lazy_parser! {
+///     /// A list of numbers, e.g. `[1,2,3]`.
+///     pub fn list_p(&str) -> Vec<u32> =
+///         lit("[").and_r(lst_sep_empt(number_p, lit(","))).and_l(lit("]"));
+///
+///     fn number_p(&str) -> u32, ParseError =
+///         reg(r"\d+").map(|s| s.parse().unwrap());
+///
+///     fn word_p(&str) -> &'a str = lit_str("[").or(reg_str(r"\w+"));
+/// }
+///
+/// assert_eq!(Ok((vec![1, 2, 3], "")), list_p("[1,2,3]"));
+/// assert_eq!(Ok((vec![], "")), parse(list_p, "[]"));
+///
+/// let inp = String::from("abc def");
+/// assert_eq!(Ok(("abc", " def")), word_p(&inp));
+/// ```
+#[macro_export]
+macro_rules! lazy_parser {
+    () => {};
+    (
+        $(#[$attr:meta])*
+        $vis:vis fn $name:ident(&$i:ty) -> $o:ty = $par:expr;
+        $($rest:tt)*
+    ) => {
+        $crate::lazy_parser! {
+            $(#[$attr])*
+            $vis fn $name(&$i) -> $o, $crate::parser::ParseError = $par;
+            $($rest)*
+        }
+    };
+    (
+        $(#[$attr:meta])*
+        $vis:vis fn $name:ident(&$i:ty) -> $o:ty, $e:ty = $par:expr;
+        $($rest:tt)*
+    ) => {
+        $(#[$attr])*
+        $vis fn $name<'a>(input: &'a $i) -> $crate::parser::ParseResult<&'a $i, $o, $e> {
+            type Lazy = ::std::boxed::Box<
+                dyn for<'a> $crate::parser::Parser<'a, $i, $o, $e> + Send + Sync,
+            >;
+            static PARSER: ::std::sync::OnceLock<Lazy> = ::std::sync::OnceLock::new();
+
+            let parser = PARSER.get_or_init(|| ::std::boxed::Box::new($par));
+            parser.parse(input)
+        }
+
+        $crate::lazy_parser! { $($rest)* }
+    };
+}
+
+pub struct ListSepParser<P1, P2, O2> {
+    elm_parser: P1,
+    sep_parser: P2,
+    _phantom: PhantomData<fn() -> O2>,
+}
+
+/// # Example:
+/// ```
+/// use parcomb::string_parser::lit;
+/// use parcomb::parser::*;
+///
+/// let par = lst_sep(lit("a"), lit(","));
+/// let inp = "a,a,a)))";
+/// let res = par.parse(inp).unwrap();
+/// assert_eq!(vec!["a", "a", "a"], res.0);
+/// assert_eq!(")))", res.1);
+///
+/// let inp2 = "a)))";
+/// let res2 = par.parse(inp2).unwrap();
+/// assert_eq!(vec!["a"], res2.0);
+/// assert_eq!(")))", res2.1);
+///
+/// let inp3 = "b)))";
+/// let res3 = par.parse(inp3);
+/// assert!(res3.is_err());
+/// ```
+impl<'a, I, O1, O2, E, P1, P2> Parser<'a, I, Vec<O1>, E> for ListSepParser<P1, P2, O2>
+where
+    I: ?Sized,
+    P1: Parser<'a, I, O1, E>,
+    P2: Parser<'a, I, O2, E>,
+    E: ParserError<I>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, Vec<O1>, E> {
+        let mut res: Vec<O1> = vec![];
+
+        let mut inp = input;
+        let err: E;
+
+        match self.elm_parser.parse(inp) {
+            Err(e) => {
+                return Err(e);
+            }
+            Ok((elm, i)) => {
+                res.push(elm);
+                inp = i;
+            }
+        }
+
+        loop {
+            let mut inp_step = inp;
+
+            match self.sep_parser.parse(inp_step) {
+                Err(e) => {
+                    err = e;
+                    break;
+                }
+                Ok((_, i)) => {
+                    inp_step = i;
+                }
+            }
+
+            match self.elm_parser.parse(inp_step) {
+                Err(e) => {
+                    err = e;
+                    break;
+                }
+                Ok((elm, i)) => {
+                    res.push(elm);
+                    inp_step = i;
+                }
+            }
+
+            inp = inp_step;
+        }
+
+        if err.is_fatal() {
+            return Err(err);
+        }
+        record_failure::<I, E>(err);
+
+        Ok((res, inp))
+    }
+}
+
+pub struct ListSepEmptyParser<P1, P2, O2> {
+    parser: OptionParser<ListSepParser<P1, P2, O2>>,
+}
+
+/// # Example:
+/// ```
+/// use parcomb::string_parser::lit;
+/// use parcomb::parser::*;
+///
+/// let par = lst_sep_empt(lit("a"), lit(","));
+/// let inp = "a,a,a)))";
+/// let res = par.parse(inp).unwrap();
+/// assert_eq!(vec!["a", "a", "a"], res.0);
+/// assert_eq!(")))", res.1);
+///
+/// let inp2 = "a)))";
+/// let res2 = par.parse(inp2).unwrap();
+/// assert_eq!(vec!["a"], res2.0);
+/// assert_eq!(")))", res2.1);
+///
+/// let inp3 = "b)))";
+/// let res3 = par.parse(inp3).unwrap();
+/// assert_eq!(Vec::<String>::new(), res3.0);
+/// assert_eq!("b)))", res3.1);
+/// ```
+impl<'a, I, O1, O2, E, P1, P2> Parser<'a, I, Vec<O1>, E> for ListSepEmptyParser<P1, P2, O2>
+where
+    I: ?Sized,
+    P1: Parser<'a, I, O1, E>,
+    P2: Parser<'a, I, O2, E>,
+    E: ParserError<I>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, Vec<O1>, E> {
+        let empt_res: Vec<O1> = vec![];
+
+        match self.parser.parse(input) {
+            Err(e) => Err(e), // fatal errors only
+            Ok((opt, i)) => match opt {
+                None => Ok((empt_res, i)),
+                Some(elms) => Ok((elms, i)),
+            },
+        }
+    }
+}
+
+pub fn lst_sep<'a, P1, P2, I, O1, O2, E>(
+    elm_parser: P1,
+    sep_parser: P2,
+) -> ListSepParser<P1, P2, O2>
+where
+    I: ?Sized,
+    P1: Parser<'a, I, O1, E>,
+    P2: Parser<'a, I, O2, E>,
+    E: ParserError<I>,
+{
+    ListSepParser {
+        elm_parser,
+        sep_parser,
+        _phantom: PhantomData,
+    }
+}
+
+pub fn lst_sep_empt<'a, P1, P2, I, O1, O2, E>(
+    elm_parser: P1,
+    sep_parser: P2,
+) -> ListSepEmptyParser<P1, P2, O2>
+where
+    I: ?Sized,
+    P1: Parser<'a, I, O1, E>,
+    P2: Parser<'a, I, O2, E>,
+    E: ParserError<I>,
+{
+    ListSepEmptyParser {
+        parser: lst_sep(elm_parser, sep_parser).opt(),
+    }
+}
+
+pub struct ChainLeftParser<P1, P2, F> {
+    term_parser: P1,
+    op_parser: P2,
+    _phantom: PhantomData<fn() -> F>,
+}
+
+/// # Example:
+/// ```
+/// use parcomb::string_parser::{lit, reg};
+/// use parcomb::parser::*;
+///
+/// let num = reg(r"\d+").map(|s| s.parse::<i64>().unwrap());
+/// let sub = lit("-").map(|_| |x, y| x - y);
+/// let par = chainl1(num, sub);
+///
+/// let inp = "10-2-3)";
+/// assert_eq!((5, ")"), par.parse(inp).unwrap());
+/// ```
+impl<'a, I, O, E, P1, P2, F> Parser<'a, I, O, E> for ChainLeftParser<P1, P2, F>
+where
+    I: ?Sized,
+    P1: Parser<'a, I, O, E>,
+    P2: Parser<'a, I, F, E>,
+    F: Fn(O, O) -> O,
+    E: ParserError<I>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        let (mut acc, mut inp) = self.term_parser.parse(input)?;
+
+        let err = loop {
+            let (op, i) = match self.op_parser.parse(inp) {
+                Err(e) => break e,
+                Ok(r) => r,
+            };
+
+            match self.term_parser.parse(i) {
+                Err(e) => break e,
+                Ok((term, i)) => {
+                    acc = op(acc, term);
+                    inp = i;
+                }
+            }
+        };
+
+        if err.is_fatal() {
+            return Err(err);
+        }
+        record_failure::<I, E>(err);
+
+        Ok((acc, inp))
+    }
+}
+
+pub struct ChainRightParser<P1, P2, F> {
+    term_parser: P1,
+    op_parser: P2,
+    _phantom: PhantomData<fn() -> F>,
+}
+
+/// # Example:
+/// ```
+/// use parcomb::string_parser::{lit, reg};
+/// use parcomb::parser::*;
+///
+/// let num = reg(r"\d+").map(|s| s.parse::<u32>().unwrap());
+/// let pow = lit("^").map(|_| |x: u32, y| x.pow(y));
+/// let par = chainr1(num, pow);
+///
+/// let inp = "2^3^2)";
+/// assert_eq!((512, ")"), par.parse(inp).unwrap());
+/// ```
+impl<'a, I, O, E, P1, P2, F> Parser<'a, I, O, E> for ChainRightParser<P1, P2, F>
+where
+    I: ?Sized,
+    P1: Parser<'a, I, O, E>,
+    P2: Parser<'a, I, F, E>,
+    F: Fn(O, O) -> O,
+    E: ParserError<I>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        let (first, mut inp) = self.term_parser.parse(input)?;
+        let mut terms: Vec<(O, F)> = vec![];
+        let mut last = first;
+
+        let err = loop {
+            let (op, i) = match self.op_parser.parse(inp) {
+                Err(e) => break e,
+                Ok(r) => r,
+            };
+
+            match self.term_parser.parse(i) {
+                Err(e) => break e,
+                Ok((term, i)) => {
+                    terms.push((last, op));
+                    last = term;
+                    inp = i;
+                }
+            }
+        };
+
+        if err.is_fatal() {
+            return Err(err);
+        }
+        record_failure::<I, E>(err);
+
+        let res = terms
+            .into_iter()
+            .rev()
+            .fold(last, |acc, (term, op)| op(term, acc));
+
+        Ok((res, inp))
+    }
+}
+
+/// Parses one or more `term`s separated by `op`, folding them from the left
+/// with the functions `op` outputs.
+pub fn chainl1<'a, P1, P2, I, O, E, F>(term: P1, op: P2) -> ChainLeftParser<P1, P2, F>
 where
     I: ?Sized,
+    P1: Parser<'a, I, O, E>,
+    P2: Parser<'a, I, F, E>,
+    F: Fn(O, O) -> O,
+    E: ParserError<I>,
 {
-    fn parse<'a>(&self, input: &'a I) -> ParseResult<&'a I, O, E>;
-
-    fn rc(self) -> RcParser<Self>
-    where
-        Self: Sized,
-    {
-        RcParser::new(self)
+    ChainLeftParser {
+        term_parser: term,
+        op_parser: op,
+        _phantom: PhantomData,
     }
+}
 
-    fn map<F, O1>(self, f: F) -> MapParser<Self, F, O>
-    where
-        Self: Sized,
-        F: Fn(O) -> O1,
-    {
-        MapParser {
-            parser: self,
-            mapper: f,
-            _phantom: PhantomData,
-        }
+/// Parses one or more `term`s separated by `op`, folding them from the right
+/// with the functions `op` outputs.
+pub fn chainr1<'a, P1, P2, I, O, E, F>(term: P1, op: P2) -> ChainRightParser<P1, P2, F>
+where
+    I: ?Sized,
+    P1: Parser<'a, I, O, E>,
+    P2: Parser<'a, I, F, E>,
+    F: Fn(O, O) -> O,
+    E: ParserError<I>,
+{
+    ChainRightParser {
+        term_parser: term,
+        op_parser: op,
+        _phantom: PhantomData,
     }
+}
 
-    fn and<P2, O2>(self, p2: P2) -> AndParser<Self, P2>
-    where
-        Self: Sized,
-        P2: Parser<I, O2, E>,
-    {
-        AndParser { p1: self, p2 }
-    }
+pub struct PeekParser<P> {
+    parser: P,
+}
 
-    fn and_l<P2, O2>(self, p2: P2) -> AndLeftParser<Self, P2, O2>
-    where
-        Self: Sized,
-        P2: Parser<I, O2, E>,
-    {
-        AndLeftParser {
-            parser: AndParser { p1: self, p2 },
-            _phantom: PhantomData,
-        }
-    }
+/// # Example:
+/// ```
+/// use parcomb::string_parser::lit;
+/// use parcomb::parser::*;
+///
+/// let par = peek(lit("abc"));
+///
+/// let inp = "abcd";
+/// assert_eq!(("abc".to_string(), "abcd"), par.parse(inp).unwrap());
+///
+/// let inp2 = "xxxx";
+/// assert!(par.parse(inp2).is_err());
+/// ```
+impl<'a, I, O, E, P> Parser<'a, I, O, E> for PeekParser<P>
+where
+    I: ?Sized,
+    P: Parser<'a, I, O, E>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        let _lookahead = LookaheadGuard::enter();
 
-    fn and_r<P2, O2>(self, p2: P2) -> AndRightParser<Self, P2, O>
-    where
-        Self: Sized,
-        P2: Parser<I, O2, E>,
-    {
-        AndRightParser {
-            parser: AndParser { p1: self, p2 },
-            _phantom: PhantomData,
-        }
+        self.parser.parse(input).map(|(o, _)| (o, input))
     }
+}
 
-    fn or<P2, O2>(self, p2: P2) -> OrParser<Self, P2>
-    where
-        Self: Sized,
-        P2: Parser<I, O2, E>,
-    {
-        OrParser { p1: self, p2 }
-    }
+pub struct NotParser<P, O> {
+    parser: P,
+    _phantom: PhantomData<fn() -> O>,
+}
 
-    fn repeat(self) -> RepeatParser<Self>
-    where
-        Self: Sized,
-    {
-        RepeatParser { parser: self }
-    }
+/// # Example:
+/// ```
+/// use parcomb::string_parser::{lit, reg};
+/// use parcomb::parser::*;
+///
+/// let par = lit("null").and_l(not(reg(r"\w")));
+///
+/// let inp = "null, 1";
+/// assert_eq!(("null".to_string(), ", 1"), par.parse(inp).unwrap());
+///
+/// let inp2 = "nullable";
+/// let err = par.parse(inp2).unwrap_err();
+/// assert!(err.expected.is_empty());
+/// assert_eq!("a", err.found);
+/// ```
+impl<'a, I, O, E, P> Parser<'a, I, (), E> for NotParser<P, O>
+where
+    I: ?Sized,
+    P: Parser<'a, I, O, E>,
+    E: ParserError<I>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, (), E> {
+        let _lookahead = LookaheadGuard::enter();
 
-    fn repeat1(self) -> RepeatOneParser<Self>
-    where
-        Self: Sized,
-    {
-        RepeatOneParser { parser: self }
+        match self.parser.parse(input) {
+            Ok(_) => Err(E::unexpected(input)),
+            Err(e) if e.is_fatal() => Err(e),
+            Err(_) => Ok(((), input)),
+        }
     }
+}
 
-    fn opt(self) -> OptionParser<Self>
-    where
-        Self: Sized,
-    {
-        OptionParser { parser: self }
-    }
+pub struct EofParser;
 
-    fn skip(self) -> SkipParser<Self, O>
-    where
-        Self: Sized,
-    {
-        SkipParser {
-            parser: self,
-            _phantom: PhantomData,
+/// # Example:
+/// ```
+/// use parcomb::string_parser::lit;
+/// use parcomb::parser::*;
+///
+/// let par = lit("abc").and_l(eof());
+///
+/// let inp = "abc";
+/// assert_eq!(("abc".to_string(), ""), par.parse(inp).unwrap());
+///
+/// let inp2 = "abcd";
+/// let err = par.parse(inp2).unwrap_err();
+/// assert_eq!(vec!["end of input"], err.expected);
+/// ```
+impl<'a, I, E> Parser<'a, I, (), E> for EofParser
+where
+    I: ?Sized + Input,
+    E: ParserError<I>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, (), E> {
+        if input.input_len() == 0 {
+            return Ok(((), input));
         }
+
+        Err(E::expected(input, "end of input"))
     }
 }
 
-pub fn parse<'a, P, I, O, E>(parser: P, input: &'a I) -> ParseResult<&'a I, O, E>
+/// Succeeds with the output of `parser` without consuming any input.
+pub fn peek<'a, P, I, O, E>(parser: P) -> PeekParser<P>
 where
     I: ?Sized,
-    P: Parser<I, O, E>,
+    P: Parser<'a, I, O, E>,
 {
-    parser.parse(input)
+    PeekParser { parser }
 }
 
-/// Implements `Parser` trait for functions & closures.
-impl<I, O, E, F> Parser<I, O, E> for F
+/// Succeeds without consuming any input if `parser` fails, and fails if it
+/// succeeds. A fatal failure of `parser` is passed through.
+pub fn not<'a, P, I, O, E>(parser: P) -> NotParser<P, O>
 where
     I: ?Sized,
-    F: Fn(&I) -> ParseResult<&I, O, E>,
+    P: Parser<'a, I, O, E>,
+    E: ParserError<I>,
 {
-    fn parse<'a>(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
-        self(input)
+    NotParser {
+        parser,
+        _phantom: PhantomData,
     }
 }
 
-pub struct MapParser<P, F, O> {
-    parser: P,
-    mapper: F,
-    _phantom: PhantomData<O>,
+/// Succeeds at the end of the input only.
+pub fn eof() -> EofParser {
+    EofParser
 }
 
-impl<I, O1, E, P, F, O> Parser<I, O1, E> for MapParser<P, F, O>
+/// The primitives over items carry their input and error types, so that
+/// those can be inferred from the parse they are used in.
+pub struct AnyParser<I, E>
 where
     I: ?Sized,
-    P: Parser<I, O, E>,
-    F: Fn(O) -> O1,
 {
-    fn parse<'a>(&self, input: &'a I) -> ParseResult<&'a I, O1, E> {
-        let (o, i) = self.parser.parse(input)?;
-        let o2 = (self.mapper)(o);
+    _phantom: PhantomData<fn(&I) -> E>,
+}
 
-        return Ok((o2, i));
+impl<'a, I, E> Parser<'a, I, I::Item<'a>, E> for AnyParser<I, E>
+where
+    I: ?Sized + Input + 'a,
+    E: ParserError<I>,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, I::Item<'a>, E> {
+        satisfy(|_: &_| true).parse(input)
     }
 }
 
-/// For chaining two parsers.
-pub struct AndParser<P1, P2> {
-    p1: P1,
-    p2: P2,
+pub struct SatisfyParser<I, F, E>
+where
+    I: ?Sized,
+{
+    pred: F,
+    _phantom: PhantomData<fn(&I) -> E>,
 }
 
-impl<I, O1, O2, E, P1, P2> Parser<I, (O1, O2), E> for AndParser<P1, P2>
+/// # Example:
+/// ```
+/// use parcomb::parser::*;
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Token {
+///     Num(i64),
+///     Plus,
+/// }
+///
+/// // num op num, returning the operator
+/// fn binop(toks: &[Token]) -> ParseResult<&[Token], &Token> {
+///     let num = || satisfy(|t: &&Token| matches!(t, Token::Num(_)));
+///
+///     num().and_r(any()).and_l(num()).parse(toks)
+/// }
+///
+/// let toks = [Token::Num(1), Token::Plus, Token::Num(2)];
+/// assert_eq!(Ok((&Token::Plus, &toks[3..])), binop(&toks));
+///
+/// let err = parse(binop, &toks[1..]).unwrap_err();
+/// assert_eq!("1:1: unexpected `Plus`", err.to_string());
+/// ```
+impl<'a, I, E, F> Parser<'a, I, I::Item<'a>, E> for SatisfyParser<I, F, E>
 where
-    I: ?Sized,
-    P1: Parser<I, O1, E>,
-    P2: Parser<I, O2, E>,
+    I: ?Sized + Input + 'a,
+    E: ParserError<I>,
+    F: Fn(&I::Item<'a>) -> bool,
 {
-    fn parse<'a>(&self, input: &'a I) -> ParseResult<&'a I, (O1, O2), E> {
-        let (o1, i1) = self.p1.parse(input)?;
-        let (o2, i2) = self.p2.parse(i1)?;
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, I::Item<'a>, E> {
+        let mut items = input.iter_items();
 
-        Ok(((o1, o2), i2))
+        match items.next() {
+            Some((_, item)) if (self.pred)(&item) => {
+                let end = items.next().map_or(input.input_len(), |(offset, _)| offset);
+                Ok((item, input.input_split_at(end).1))
+            }
+            _ => Err(E::unexpected(input)),
+        }
     }
 }
 
-pub struct AndLeftParser<P1, P2, O2> {
-    parser: AndParser<P1, P2>,
-    _phantom: PhantomData<O2>,
+pub struct TakeParser<I, E>
+where
+    I: ?Sized,
+{
+    n: usize,
+    _phantom: PhantomData<fn(&I) -> E>,
 }
 
 /// # Example:
 /// ```
-/// use parcomb::string_parser::lit;
 /// use parcomb::parser::*;
 ///
-/// let par = lit("abc").and_l(lit("def"));
-///
-/// let inp = "abcdefg";
-/// let res = par.parse(inp).unwrap();
-/// assert_eq!(("abc".to_string(), "g"), res);
+/// let res: ParseResult<&str, &str> = take(2).parse("héllo");
+/// assert_eq!(Ok(("hé", "llo")), res);
 ///
-/// let inp2 = "xxxx";
-/// assert!(par.parse(inp2).is_err());
+/// let err: ParseError = parse(take(3), &[1, 2][..]).unwrap_err();
+/// assert_eq!("1:3: unexpected end of input", err.to_string());
 /// ```
-impl<I, O1, O2, E, P1, P2> Parser<I, O1, E> for AndLeftParser<P1, P2, O2>
+impl<'a, I, E> Parser<'a, I, &'a I, E> for TakeParser<I, E>
 where
-    I: ?Sized,
-    P1: Parser<I, O1, E>,
-    P2: Parser<I, O2, E>,
+    I: ?Sized + Input,
+    E: ParserError<I>,
 {
-    fn parse<'a>(&self, input: &'a I) -> ParseResult<&'a I, O1, E> {
-        self.parser.parse(input).map(|((o1, _), i)| (o1, i))
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, &'a I, E> {
+        match items_end(input, self.n) {
+            Some(end) => Ok(input.input_split_at(end)),
+            None => Err(E::unexpected(input.input_split_at(input.input_len()).1)),
+        }
     }
 }
 
-pub struct AndRightParser<P1, P2, O1> {
-    parser: AndParser<P1, P2>,
-    _phantom: PhantomData<O1>,
+pub struct TakeWhileParser<I, F, E>
+where
+    I: ?Sized,
+{
+    pred: F,
+    _phantom: PhantomData<fn(&I) -> E>,
 }
 
 /// # Example:
 /// ```
-/// use parcomb::string_parser::lit;
 /// use parcomb::parser::*;
 ///
-/// let par = lit("abc").and_r(lit("def"));
-///
-/// let inp = "abcdefg";
-/// let res = par.parse(inp).unwrap();
-/// assert_eq!(("def".to_string(), "g"), res);
+/// let digits = take_while(|c: &char| c.is_ascii_digit());
+/// let res: ParseResult<&str, &str> = digits.parse("123abc");
+/// assert_eq!(Ok(("123", "abc")), res);
 ///
-/// let inp2 = "xxxx";
-/// assert!(par.parse(inp2).is_err());
+/// let bytes: &[u8] = b"  x";
+/// let spaces = take_while(|b: &&u8| **b == b' ');
+/// let res: ParseResult<&[u8], &[u8]> = spaces.parse(bytes);
+/// assert_eq!(Ok((&bytes[..2], &bytes[2..])), res);
 /// ```
-impl<I, O1, O2, E, P1, P2> Parser<I, O2, E> for AndRightParser<P1, P2, O1>
+impl<'a, I, E, F> Parser<'a, I, &'a I, E> for TakeWhileParser<I, F, E>
+where
+    I: ?Sized + Input + 'a,
+    F: Fn(&I::Item<'a>) -> bool,
+{
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, &'a I, E> {
+        let end = input
+            .iter_items()
+            .find(|(_, item)| !(self.pred)(item))
+            .map_or(input.input_len(), |(offset, _)| offset);
+
+        Ok(input.input_split_at(end))
+    }
+}
+
+/// Matches any single item of the input, and fails at the end of input.
+pub fn any<I, E>() -> AnyParser<I, E>
 where
     I: ?Sized,
-    P1: Parser<I, O1, E>,
-    P2: Parser<I, O2, E>,
 {
-    fn parse<'a>(&self, input: &'a I) -> ParseResult<&'a I, O2, E> {
-        self.parser.parse(input).map(|((_, o2), i)| (o2, i))
+    AnyParser {
+        _phantom: PhantomData,
     }
 }
 
-pub struct OrParser<P1, P2> {
-    p1: P1,
-    p2: P2,
+/// Matches a single item of the input if `pred` holds for it.
+pub fn satisfy<I, F, E>(pred: F) -> SatisfyParser<I, F, E>
+where
+    I: ?Sized,
+{
+    SatisfyParser {
+        pred,
+        _phantom: PhantomData,
+    }
+}
+
+/// Matches exactly `n` items of the input, and returns the slice of the input
+/// they make up.
+pub fn take<I, E>(n: usize) -> TakeParser<I, E>
+where
+    I: ?Sized,
+{
+    TakeParser {
+        n,
+        _phantom: PhantomData,
+    }
+}
+
+/// Matches items of the input as long as `pred` holds for them, and returns
+/// the slice of the input they make up.
+pub fn take_while<I, F, E>(pred: F) -> TakeWhileParser<I, F, E>
+where
+    I: ?Sized,
+{
+    TakeWhileParser {
+        pred,
+        _phantom: PhantomData,
+    }
+}
+
+/// The associativity of an infix operator of a `PrattParser`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assoc {
+    Left,
+    Right,
+}
+
+type UnaryFold<'s, O> = Box<dyn FnOnce(O) -> O + 's>;
+type BinaryFold<'s, O> = Box<dyn FnOnce(O, O) -> O + 's>;
+
+/// A prefix or postfix operator parsed by `Operators`, with its precedence.
+type UnaryMatch<'a, 's, I, O, E> = Option<ParseResult<&'a I, (u32, UnaryFold<'s, O>), E>>;
+/// An infix operator parsed by `Operators`, with its associativity and
+/// precedence.
+type BinaryMatch<'a, 's, I, O, E> = Option<ParseResult<&'a I, (Assoc, u32, BinaryFold<'s, O>), E>>;
+
+/// A prefix operator of a `PrattParser`.
+pub struct Prefix<P, F, OO> {
+    precedence: u32,
+    parser: P,
+    fold: F,
+    _phantom: PhantomData<fn() -> OO>,
+}
+
+/// An infix operator of a `PrattParser`.
+pub struct Infix<P, F, OO> {
+    assoc: Assoc,
+    precedence: u32,
+    parser: P,
+    fold: F,
+    _phantom: PhantomData<fn() -> OO>,
+}
+
+/// A postfix operator of a `PrattParser`.
+pub struct Postfix<P, F, OO> {
+    precedence: u32,
+    parser: P,
+    fold: F,
+    _phantom: PhantomData<fn() -> OO>,
+}
+
+/// The operators of a `PrattParser`: `()` for none, and nested pairs of the
+/// operators added before and the last one.
+///
+/// Each method tries the operators of one kind in the order they were added,
+/// and returns the first that matches, or the first fatal failure. Other
+/// failures are passed to `failed`.
+pub trait Operators<'a, I, O, E>
+where
+    I: ?Sized,
+{
+    /// Parses a prefix operator, returning its precedence and how to fold its
+    /// operand.
+    fn parse_prefix<'s>(
+        &'s self,
+        input: &'a I,
+        failed: &mut dyn FnMut(E),
+    ) -> UnaryMatch<'a, 's, I, O, E>
+    where
+        'a: 's;
+
+    /// Parses an infix operator, returning its associativity, precedence and
+    /// how to fold its operands.
+    fn parse_infix<'s>(
+        &'s self,
+        input: &'a I,
+        failed: &mut dyn FnMut(E),
+    ) -> BinaryMatch<'a, 's, I, O, E>
+    where
+        'a: 's;
+
+    /// Parses a postfix operator, returning its precedence and how to fold
+    /// its operand.
+    fn parse_postfix<'s>(
+        &'s self,
+        input: &'a I,
+        failed: &mut dyn FnMut(E),
+    ) -> UnaryMatch<'a, 's, I, O, E>
+    where
+        'a: 's;
+}
+
+impl<'a, I, O, E> Operators<'a, I, O, E> for ()
+where
+    I: ?Sized,
+{
+    fn parse_prefix<'s>(
+        &'s self,
+        _input: &'a I,
+        _failed: &mut dyn FnMut(E),
+    ) -> UnaryMatch<'a, 's, I, O, E>
+    where
+        'a: 's,
+    {
+        None
+    }
+
+    fn parse_infix<'s>(
+        &'s self,
+        _input: &'a I,
+        _failed: &mut dyn FnMut(E),
+    ) -> BinaryMatch<'a, 's, I, O, E>
+    where
+        'a: 's,
+    {
+        None
+    }
+
+    fn parse_postfix<'s>(
+        &'s self,
+        _input: &'a I,
+        _failed: &mut dyn FnMut(E),
+    ) -> UnaryMatch<'a, 's, I, O, E>
+    where
+        'a: 's,
+    {
+        None
+    }
 }
 
-impl<I, O, E, P1, P2> Parser<I, O, E> for OrParser<P1, P2>
+/// Parses the operator `parser`, handing a recoverable failure to `failed`.
+fn parse_operator<'a, P, I, OO, E>(
+    parser: &P,
+    input: &'a I,
+    failed: &mut dyn FnMut(E),
+) -> Option<ParseResult<&'a I, OO, E>>
 where
     I: ?Sized,
-    P1: Parser<I, O, E>,
-    P2: Parser<I, O, E>,
+    P: Parser<'a, I, OO, E>,
+    E: ParserError<I>,
 {
-    fn parse<'a>(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
-        self.p1.parse(input).or_else(|_| self.p2.parse(input))
+    match parser.parse(input) {
+        Err(e) if !e.is_fatal() => {
+            failed(e);
+            None
+        }
+        res => Some(res),
     }
 }
 
-pub struct RepeatParser<P> {
-    parser: P,
-}
-
-impl<I, O, E, P> Parser<I, Vec<O>, E> for RepeatParser<P>
+impl<'a, I, O, E, Ops, P, F, OO> Operators<'a, I, O, E> for (Ops, Prefix<P, F, OO>)
 where
     I: ?Sized,
-    P: Parser<I, O, E>,
+    Ops: Operators<'a, I, O, E>,
+    P: Parser<'a, I, OO, E>,
+    F: Fn(OO, O) -> O,
+    OO: 'a,
+    E: ParserError<I>,
 {
-    fn parse<'a>(&self, input: &'a I) -> ParseResult<&'a I, Vec<O>, E> {
-        let mut res: Vec<O> = Vec::new();
-
-        let mut i = input;
-        loop {
-            match self.parser.parse(i) {
-                Err(_) => {
-                    break;
-                }
-
-                Ok((o, i1)) => {
-                    res.push(o);
-                    i = i1;
-                }
-            }
+    fn parse_prefix<'s>(
+        &'s self,
+        input: &'a I,
+        failed: &mut dyn FnMut(E),
+    ) -> UnaryMatch<'a, 's, I, O, E>
+    where
+        'a: 's,
+    {
+        let (ops, op) = self;
+        if let Some(res) = ops.parse_prefix(input, failed) {
+            return Some(res);
         }
 
-        return Ok((res, i));
+        parse_operator(&op.parser, input, failed).map(|res| {
+            res.map(|(oo, i)| {
+                let fold: UnaryFold<'s, O> = Box::new(move |o| (op.fold)(oo, o));
+                ((op.precedence, fold), i)
+            })
+        })
     }
-}
 
-pub struct RepeatOneParser<P> {
-    parser: P,
+    fn parse_infix<'s>(
+        &'s self,
+        input: &'a I,
+        failed: &mut dyn FnMut(E),
+    ) -> BinaryMatch<'a, 's, I, O, E>
+    where
+        'a: 's,
+    {
+        self.0.parse_infix(input, failed)
+    }
+
+    fn parse_postfix<'s>(
+        &'s self,
+        input: &'a I,
+        failed: &mut dyn FnMut(E),
+    ) -> UnaryMatch<'a, 's, I, O, E>
+    where
+        'a: 's,
+    {
+        self.0.parse_postfix(input, failed)
+    }
 }
 
-impl<I, O, E, P> Parser<I, Vec<O>, E> for RepeatOneParser<P>
+impl<'a, I, O, E, Ops, P, F, OO> Operators<'a, I, O, E> for (Ops, Infix<P, F, OO>)
 where
     I: ?Sized,
-    P: Parser<I, O, E>,
+    Ops: Operators<'a, I, O, E>,
+    P: Parser<'a, I, OO, E>,
+    F: Fn(OO, O, O) -> O,
+    OO: 'a,
+    E: ParserError<I>,
 {
-    fn parse<'a>(&self, input: &'a I) -> ParseResult<&'a I, Vec<O>, E> {
-        let mut res: Vec<O> = Vec::new();
-
-        let mut i = input;
-        let e: E;
-        loop {
-            match self.parser.parse(i) {
-                Err(e0) => {
-                    e = e0;
-                    break;
-                }
-
-                Ok((o, i1)) => {
-                    res.push(o);
-                    i = i1;
-                }
-            }
-        }
+    fn parse_prefix<'s>(
+        &'s self,
+        input: &'a I,
+        failed: &mut dyn FnMut(E),
+    ) -> UnaryMatch<'a, 's, I, O, E>
+    where
+        'a: 's,
+    {
+        self.0.parse_prefix(input, failed)
+    }
 
-        if res.len() >= 1 {
-            return Ok((res, i));
+    fn parse_infix<'s>(
+        &'s self,
+        input: &'a I,
+        failed: &mut dyn FnMut(E),
+    ) -> BinaryMatch<'a, 's, I, O, E>
+    where
+        'a: 's,
+    {
+        let (ops, op) = self;
+        if let Some(res) = ops.parse_infix(input, failed) {
+            return Some(res);
         }
 
-        return Err(e);
+        parse_operator(&op.parser, input, failed).map(|res| {
+            res.map(|(oo, i)| {
+                let fold: BinaryFold<'s, O> = Box::new(move |l, r| (op.fold)(oo, l, r));
+                ((op.assoc, op.precedence, fold), i)
+            })
+        })
     }
-}
 
-pub struct OptionParser<P> {
-    parser: P,
+    fn parse_postfix<'s>(
+        &'s self,
+        input: &'a I,
+        failed: &mut dyn FnMut(E),
+    ) -> UnaryMatch<'a, 's, I, O, E>
+    where
+        'a: 's,
+    {
+        self.0.parse_postfix(input, failed)
+    }
 }
 
-/// # Example:
-/// ```
-/// use parcomb::string_parser::lit;
-/// use parcomb::parser::*;
-///
-/// let par = lit("abc").opt();
-///
-/// let inp = "abcd";
-/// let res = par.parse(inp).unwrap();
-/// assert_eq!((Some("abc".to_string()), "d"), res);
-///
-/// let inp2 = "xxxx";
-/// let res = par.parse(inp2).unwrap();
-/// assert_eq!((None, "xxxx"), res);
-/// ```
-impl<I, O, E, P> Parser<I, Option<O>, E> for OptionParser<P>
+impl<'a, I, O, E, Ops, P, F, OO> Operators<'a, I, O, E> for (Ops, Postfix<P, F, OO>)
 where
     I: ?Sized,
-    P: Parser<I, O, E>,
+    Ops: Operators<'a, I, O, E>,
+    P: Parser<'a, I, OO, E>,
+    F: Fn(OO, O) -> O,
+    OO: 'a,
+    E: ParserError<I>,
 {
-    fn parse<'a>(&self, input: &'a I) -> ParseResult<&'a I, Option<O>, E> {
-        match self.parser.parse(input) {
-            Ok((r, i)) => Ok((Some(r), i)),
-            Err(_) => Ok((None, input)),
-        }
+    fn parse_prefix<'s>(
+        &'s self,
+        input: &'a I,
+        failed: &mut dyn FnMut(E),
+    ) -> UnaryMatch<'a, 's, I, O, E>
+    where
+        'a: 's,
+    {
+        self.0.parse_prefix(input, failed)
     }
-}
 
-pub struct SkipParser<P, O> {
-    parser: P,
-    _phantom: PhantomData<O>,
-}
+    fn parse_infix<'s>(
+        &'s self,
+        input: &'a I,
+        failed: &mut dyn FnMut(E),
+    ) -> BinaryMatch<'a, 's, I, O, E>
+    where
+        'a: 's,
+    {
+        self.0.parse_infix(input, failed)
+    }
 
-/// # Example:
-/// ```
-/// use parcomb::string_parser::lit;
-/// use parcomb::parser::*;
-///
-/// let par = lit("abc").skip();
-///
-/// let inp = "abcd";
-/// let res = par.parse(inp).unwrap();
-/// assert_eq!(((), "d"), res);
-///
-/// let inp2 = "xxxx";
-/// assert!(par.parse(inp2).is_err());
-/// ```
-impl<I, O, E, P> Parser<I, (), E> for SkipParser<P, O>
-where
-    I: ?Sized,
-    P: Parser<I, O, E>,
-{
-    fn parse<'a>(&self, input: &'a I) -> ParseResult<&'a I, (), E> {
-        self.parser.parse(input).map(|(_, i)| ((), i))
+    fn parse_postfix<'s>(
+        &'s self,
+        input: &'a I,
+        failed: &mut dyn FnMut(E),
+    ) -> UnaryMatch<'a, 's, I, O, E>
+    where
+        'a: 's,
+    {
+        let (ops, op) = self;
+        if let Some(res) = ops.parse_postfix(input, failed) {
+            return Some(res);
+        }
+
+        parse_operator(&op.parser, input, failed).map(|res| {
+            res.map(|(oo, i)| {
+                let fold: UnaryFold<'s, O> = Box::new(move |o| (op.fold)(oo, o));
+                ((op.precedence, fold), i)
+            })
+        })
     }
 }
 
-pub struct RcParser<P> {
-    parser: Rc<P>,
+/// An operator-precedence parser: parses atoms joined by prefix, infix and
+/// postfix operators, see [`pratt`].
+///
+/// Operators are given a precedence, the higher the tighter they bind. Each
+/// operator is a parser, whose output is passed to the fold function of the
+/// operator along with the operands.
+///
+/// The parser is `Send + Sync` if its atom, operators and fold functions are,
+/// e.g. to define it with `lazy_parser!`.
+pub struct PrattParser<A, Ops> {
+    atom: A,
+    ops: Ops,
 }
 
-impl<P> RcParser<P> {
-    pub fn new<I, O, E>(p: P) -> Self
+impl<A, Ops> PrattParser<A, Ops> {
+    /// Adds a prefix operator, `fold` gets the operator and its operand.
+    pub fn prefix<'a, P, F, I, O, OO, E>(
+        self,
+        precedence: u32,
+        op: P,
+        fold: F,
+    ) -> PrattParser<A, (Ops, Prefix<P, F, OO>)>
     where
         I: ?Sized,
-        P: Parser<I, O, E>,
+        A: Parser<'a, I, O, E>,
+        P: Parser<'a, I, OO, E>,
+        F: Fn(OO, O) -> O,
     {
-        Self { parser: Rc::new(p) }
+        let op = Prefix {
+            precedence,
+            parser: op,
+            fold,
+            _phantom: PhantomData,
+        };
+
+        PrattParser {
+            atom: self.atom,
+            ops: (self.ops, op),
+        }
     }
-}
 
-impl<I, O, E, P> Parser<I, O, E> for RcParser<P>
-where
-    I: ?Sized,
-    P: Parser<I, O, E>,
-{
-    fn parse<'a>(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
-        self.parser.parse(input)
+    /// Adds an infix operator, `fold` gets the operator and its operands.
+    pub fn infix<'a, P, F, I, O, OO, E>(
+        self,
+        assoc: Assoc,
+        precedence: u32,
+        op: P,
+        fold: F,
+    ) -> PrattParser<A, (Ops, Infix<P, F, OO>)>
+    where
+        I: ?Sized,
+        A: Parser<'a, I, O, E>,
+        P: Parser<'a, I, OO, E>,
+        F: Fn(OO, O, O) -> O,
+    {
+        let op = Infix {
+            assoc,
+            precedence,
+            parser: op,
+            fold,
+            _phantom: PhantomData,
+        };
+
+        PrattParser {
+            atom: self.atom,
+            ops: (self.ops, op),
+        }
     }
-}
 
-impl<P> Clone for RcParser<P> {
-    fn clone(&self) -> Self {
-        Self {
-            parser: self.parser.clone(),
+    /// Adds a postfix operator, `fold` gets the operator and its operand.
+    pub fn postfix<'a, P, F, I, O, OO, E>(
+        self,
+        precedence: u32,
+        op: P,
+        fold: F,
+    ) -> PrattParser<A, (Ops, Postfix<P, F, OO>)>
+    where
+        I: ?Sized,
+        A: Parser<'a, I, O, E>,
+        P: Parser<'a, I, OO, E>,
+        F: Fn(OO, O) -> O,
+    {
+        let op = Postfix {
+            precedence,
+            parser: op,
+            fold,
+            _phantom: PhantomData,
+        };
+
+        PrattParser {
+            atom: self.atom,
+            ops: (self.ops, op),
         }
     }
 }
 
-pub struct ListSepParser<P1, P2, O2> {
-    elm_parser: P1,
-    sep_parser: P2,
-    _phantom: PhantomData<O2>,
+/// Returns the binding power of an operator of precedence `prec`, leaving
+/// room for the one of its right operand. It is widened to `u64`, so that
+/// any `u32` precedence fits.
+fn binding_power(prec: u32) -> u64 {
+    2 * u64::from(prec)
 }
 
-/// # Example:
-/// ```
-/// use parcomb::string_parser::lit;
-/// use parcomb::parser::*;
-///
-/// let par = lst_sep(lit("a"), lit(","));
-/// let inp = "a,a,a)))";
-/// let res = par.parse(inp).unwrap();
-/// assert_eq!(vec!["a", "a", "a"], res.0);
-/// assert_eq!(")))", res.1);
-///
-/// let inp2 = "a)))";
-/// let res2 = par.parse(inp2).unwrap();
-/// assert_eq!(vec!["a"], res2.0);
-/// assert_eq!(")))", res2.1);
-///
-/// let inp3 = "b)))";
-/// let res3 = par.parse(inp3);
-/// assert!(res3.is_err());
-/// ```
-impl<I, O1, O2, E, P1, P2> Parser<I, Vec<O1>, E> for ListSepParser<P1, P2, O2>
-where
-    I: ?Sized,
-    P1: Parser<I, O1, E>,
-    P2: Parser<I, O2, E>,
-{
-    fn parse<'a>(&self, input: &'a I) -> ParseResult<&'a I, Vec<O1>, E> {
-        let mut res: Vec<O1> = vec![];
+impl<A, Ops> PrattParser<A, Ops> {
+    /// Parses an expression whose operators bind at least as tight as `min_bp`.
+    fn parse_expr<'a, I, O, E>(&self, input: &'a I, min_bp: u64) -> ParseResult<&'a I, O, E>
+    where
+        I: ?Sized,
+        A: Parser<'a, I, O, E>,
+        Ops: Operators<'a, I, O, E>,
+        E: ParserError<I>,
+    {
+        let (mut lhs, mut i) = self.parse_operand(input)?;
+        let mut record = |e| record_failure::<I, E>(e);
 
-        let mut inp = input;
-        let err: E;
+        loop {
+            match self.ops.parse_postfix(i, &mut record) {
+                Some(Ok(((prec, fold), i1))) => {
+                    if binding_power(prec) < min_bp {
+                        break;
+                    }
 
-        match self.elm_parser.parse(inp) {
-            Err(e) => {
-                return Err(e);
-            }
-            Ok((elm, i)) => {
-                res.push(elm);
-                inp = i;
+                    lhs = fold(lhs);
+                    i = i1;
+                    continue;
+                }
+                Some(Err(e)) => return Err(e),
+                None => {}
             }
-        }
 
-        loop {
-            let mut inp_step = inp;
+            match self.ops.parse_infix(i, &mut record) {
+                Some(Ok(((assoc, prec, fold), i1))) => {
+                    let (l_bp, r_bp) = match assoc {
+                        Assoc::Left => (binding_power(prec), binding_power(prec) + 1),
+                        Assoc::Right => (binding_power(prec) + 1, binding_power(prec)),
+                    };
+                    if l_bp < min_bp {
+                        break;
+                    }
 
-            match self.sep_parser.parse(inp_step) {
-                Err(e) => {
-                    err = e;
-                    break;
-                }
-                Ok((_, i)) => {
-                    inp_step = i;
+                    let (rhs, i2) = self.parse_expr(i1, r_bp)?;
+                    lhs = fold(lhs, rhs);
+                    i = i2;
                 }
+                Some(Err(e)) => return Err(e),
+                None => break,
             }
+        }
 
-            match self.elm_parser.parse(inp_step) {
-                Err(e) => {
-                    err = e;
-                    break;
-                }
-                Ok((elm, i)) => {
-                    res.push(elm);
-                    inp_step = i;
+        Ok((lhs, i))
+    }
+
+    /// Parses an atom, or a prefix operator and its operand.
+    fn parse_operand<'a, I, O, E>(&self, input: &'a I) -> ParseResult<&'a I, O, E>
+    where
+        I: ?Sized,
+        A: Parser<'a, I, O, E>,
+        Ops: Operators<'a, I, O, E>,
+        E: ParserError<I>,
+    {
+        let mut err: Option<E> = None;
+        let prefix = self.ops.parse_prefix(input, &mut |e| {
+            err = Some(match err.take() {
+                Some(err) => err.merge(e),
+                None => e,
+            });
+        });
+
+        match prefix {
+            Some(Ok(((prec, fold), i))) => {
+                if let Some(e) = err {
+                    record_failure::<I, E>(e);
                 }
-            }
 
-            inp = inp_step;
+                let (o, i1) = self.parse_expr(i, binding_power(prec))?;
+                return Ok((fold(o), i1));
+            }
+            Some(Err(e)) => return Err(e),
+            None => {}
         }
 
-        if res.len() > 0 {
-            return Ok((res, inp));
+        match (self.atom.parse(input), err) {
+            (Ok(r), Some(e)) => {
+                record_failure::<I, E>(e);
+                Ok(r)
+            }
+            (Err(e), Some(err)) if !e.is_fatal() => Err(err.merge(e)),
+            (res, _) => res,
         }
-
-        return Err(err);
     }
 }
 
-pub struct ListSepEmptyParser<P1, P2, O2> {
-    parser: OptionParser<ListSepParser<P1, P2, O2>>,
-}
-
 /// # Example:
 /// ```
-/// use parcomb::string_parser::lit;
+/// use parcomb::string_parser::{lit_sp, reg};
 /// use parcomb::parser::*;
 ///
-/// let par = lst_sep_empt(lit("a"), lit(","));
-/// let inp = "a,a,a)))";
-/// let res = par.parse(inp).unwrap();
-/// assert_eq!(vec!["a", "a", "a"], res.0);
-/// assert_eq!(")))", res.1);
-///
-/// let inp2 = "a)))";
-/// let res2 = par.parse(inp2).unwrap();
-/// assert_eq!(vec!["a"], res2.0);
-/// assert_eq!(")))", res2.1);
+/// let num = reg(r"\d+").map(|s| s.parse::<i64>().unwrap());
+/// let par = pratt(num)
+///     .prefix(3, lit_sp("-"), |_, x| -x)
+///     .infix(Assoc::Left, 1, lit_sp("-"), |_, x, y| x - y)
+///     .infix(Assoc::Left, 2, lit_sp("*"), |_, x, y| x * y)
+///     .infix(Assoc::Right, 4, lit_sp("^"), |_, x, y| x.pow(y as u32));
 ///
-/// let inp3 = "b)))";
-/// let res3 = par.parse(inp3).unwrap();
-/// assert_eq!(Vec::<String>::new(), res3.0);
-/// assert_eq!("b)))", res3.1);
+/// let inp = "10 - 2 * 3 - -2^3^0";
+/// assert_eq!((6, ""), par.parse(inp).unwrap());
 /// ```
-impl<I, O1, O2, E, P1, P2> Parser<I, Vec<O1>, E> for ListSepEmptyParser<P1, P2, O2>
-where
-    I: ?Sized,
-    P1: Parser<I, O1, E>,
-    P2: Parser<I, O2, E>,
-{
-    fn parse<'a>(&self, input: &'a I) -> ParseResult<&'a I, Vec<O1>, E> {
-        let empt_res: Vec<O1> = vec![];
-
-        match self.parser.parse(input) {
-            Err(_) => Ok((empt_res, input)), // should never happend
-            Ok((opt, i)) => match opt {
-                None => Ok((empt_res, i)),
-                Some(elms) => Ok((elms, i)),
-            },
-        }
-    }
-}
-
-pub fn lst_sep<P1, P2, I, O1, O2, E>(elm_parser: P1, sep_parser: P2) -> ListSepParser<P1, P2, O2>
+impl<'a, A, Ops, I, O, E> Parser<'a, I, O, E> for PrattParser<A, Ops>
 where
     I: ?Sized,
-    P1: Parser<I, O1, E>,
-    P2: Parser<I, O2, E>,
+    A: Parser<'a, I, O, E>,
+    Ops: Operators<'a, I, O, E>,
+    E: ParserError<I>,
 {
-    ListSepParser {
-        elm_parser,
-        sep_parser,
-        _phantom: PhantomData,
+    fn parse(&self, input: &'a I) -> ParseResult<&'a I, O, E> {
+        self.parse_expr(input, 0)
     }
 }
 
-pub fn lst_sep_empt<P1, P2, I, O1, O2, E>(
-    elm_parser: P1,
-    sep_parser: P2,
-) -> ListSepEmptyParser<P1, P2, O2>
+/// Creates a `PrattParser` for expressions over `atom`, without operators.
+pub fn pratt<'a, A, I, O, E>(atom: A) -> PrattParser<A, ()>
 where
     I: ?Sized,
-    P1: Parser<I, O1, E>,
-    P2: Parser<I, O2, E>,
+    A: Parser<'a, I, O, E>,
+    E: ParserError<I>,
 {
-    ListSepEmptyParser {
-        parser: lst_sep(elm_parser, sep_parser).opt(),
-    }
+    PrattParser { atom, ops: () }
 }
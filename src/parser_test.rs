@@ -1,7 +1,1065 @@
 use super::parser::*;
+use super::string_parser::*;
+
 use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::thread;
 
 #[test]
 fn test_parser() {
-    assert!(true);
+    let res = parse(lit("a").and(lit("b")), "abc");
+    assert_eq!(Ok((("a".to_string(), "b".to_string()), "c")), res);
+}
+
+#[test]
+fn test_parse_error_position() {
+    let par = lit("{")
+        .and(lit_sp("\"a\""))
+        .and(lit_sp(":"))
+        .and(reg(r"\d+"));
+
+    let inp = "{\n  \"a\" :\n\tx }";
+    let err = parse(par, inp).unwrap_err();
+
+    assert_eq!(11, err.offset);
+    assert_eq!(3, err.line);
+    assert_eq!(2, err.column);
+    assert_eq!(vec![r"/\d+/"], err.expected);
+    assert_eq!("x", err.found);
+}
+
+#[test]
+fn test_parse_error_display() {
+    let err = parse(lit("abc"), "").unwrap_err();
+    assert_eq!("1:1: expected `abc`, found end of input", err.to_string());
+
+    let err = parse(lit("ab").and(lit("c")), "abé").unwrap_err();
+    assert_eq!("1:3: expected `c`, found `é`", err.to_string());
+}
+
+#[test]
+fn test_or_merges_expected() {
+    let par = lit("null").or(lit("true")).or(lit("false")).or(lit("["));
+
+    let err = parse(par, "nope").unwrap_err();
+    assert_eq!(vec!["`null`", "`true`", "`false`", "`[`"], err.expected);
+    assert_eq!(
+        "1:1: expected one of: `null`, `true`, `false`, `[`, found `n`",
+        err.to_string()
+    );
+}
+
+#[test]
+fn test_or_keeps_furthest_error() {
+    let par = lit("a").and(lit("b")).or(lit("c").and(lit("d")));
+
+    // the first branch got further than the second one
+    let err = parse(par, "ax").unwrap_err();
+    assert_eq!(1, err.offset);
+    assert_eq!(vec!["`b`"], err.expected);
+
+    // the same holds when the order of the branches is swapped
+    let par = lit("c").and(lit("d")).or(lit("a").and(lit("b")));
+    let err = parse(par, "ax").unwrap_err();
+    assert_eq!(1, err.offset);
+    assert_eq!(vec!["`b`"], err.expected);
+}
+
+#[test]
+fn test_parse_reports_furthest_failure() {
+    let par = lit("[")
+        .and(lst_sep_empt(reg(r"\d+"), lit(",")))
+        .and(lit("]"));
+
+    // the list stops before ",x" and `]` fails there, but the element
+    // parser got further
+    let err = parse(par, "[1,2,x]").unwrap_err();
+    assert_eq!(5, err.offset);
+    assert_eq!(vec![r"/\d+/"], err.expected);
+    assert_eq!("x", err.found);
+}
+
+#[test]
+fn test_parse_merges_recovered_failure() {
+    let par = lit("a").opt().and(lit("b"));
+
+    let err = parse(par, "c").unwrap_err();
+    assert_eq!(vec!["`a`", "`b`"], err.expected);
+}
+
+#[test]
+fn test_parse_ignores_recovered_failure_on_success() {
+    let par = lit("a").repeat().and(lit("b"));
+
+    let res = parse(par, "aab");
+    assert_eq!(
+        Ok((
+            (vec!["a".to_string(), "a".to_string()], "b".to_string()),
+            ""
+        )),
+        res
+    );
+}
+
+#[test]
+fn test_cut_stops_backtracking() {
+    let pair = lit("(").and_r(reg(r"\d+").and_l(lit(")")).cut());
+    let par = pair.or(reg(r"\(\w+\)"));
+
+    // without `cut`, `(ab)` would be parsed by the second branch
+    let err = parse(par, "(ab)").unwrap_err();
+    assert!(err.fatal);
+    assert_eq!(1, err.offset);
+    assert_eq!(vec![r"/\d+/"], err.expected);
+}
+
+#[test]
+fn test_cut_stops_repeat_and_opt() {
+    let item = || lit("<").and_r(lit("a").cut()).and_l(lit(">"));
+
+    let err = parse(item().repeat(), "<a><a><b>").unwrap_err();
+    assert!(err.fatal);
+    assert_eq!(7, err.offset);
+
+    let err = parse(lst_sep(item(), lit(",")), "<a>,<b>").unwrap_err();
+    assert!(err.fatal);
+    assert_eq!(5, err.offset);
+
+    let err = parse(item().opt(), "<b>").unwrap_err();
+    assert!(err.fatal);
+    assert_eq!(1, err.offset);
+
+    // failures before the cut are still recovered from
+    let res = parse(item().repeat(), "<a>!");
+    assert_eq!(Ok((vec!["a".to_string()], "!")), res);
+}
+
+#[test]
+fn test_label_names_alternatives() {
+    let num = reg(r"\d+").label("number");
+    let str = reg(r#""[^"]*""#).label("string");
+    let par = lit("null").or(num).or(str).label("value").or(lit("["));
+
+    let err = parse(par, "?").unwrap_err();
+    assert_eq!(vec!["value", "`[`"], err.expected);
+}
+
+#[test]
+fn test_label_replaces_recovered_failures() {
+    let err = parse(lit("a").opt().and(lit("b")).label("ab"), "c").unwrap_err();
+    assert_eq!(vec!["ab"], err.expected);
+
+    let par = lit("y")
+        .repeat()
+        .and_r(lit("z"))
+        .or(lit("x"))
+        .label("thing");
+    let err = parse(par, "w").unwrap_err();
+    assert_eq!(vec!["thing"], err.expected);
+
+    // past the start of the label, recovered failures are kept
+    let par = lit("(").and(lit("a").opt()).and(lit(")")).label("group");
+    let err = parse(par, "(b").unwrap_err();
+    assert_eq!(vec!["`a`", "`)`"], err.expected);
+}
+
+#[test]
+fn test_label_keeps_deeper_failure() {
+    let par = lit("(").and(lit(")")).label("unit");
+
+    let err = parse(par, "(x").unwrap_err();
+    assert_eq!(vec!["`)`"], err.expected);
+}
+
+#[test]
+fn test_context_chain_of_recovered_failure() {
+    let item = lit("a").or(lit("b")).context("in item");
+    let list = lit("[")
+        .and(lst_sep_empt(item, lit(",")))
+        .and(lit("]"))
+        .context("in list");
+    let par = lit("(").and(list).context("in group");
+
+    // the item failure is recovered from by `lst_sep_empt`, and reported
+    // with all the frames that were active at that time
+    let err = parse(par, "([a,c])").unwrap_err();
+    assert_eq!(4, err.offset);
+    assert_eq!(vec!["in item", "in list", "in group"], err.context());
+}
+
+#[test]
+fn test_spanned() {
+    let ident = reg(r"[a-z]+").spanned();
+    let par = lst_sep(ident, lit_sp("."));
+
+    let inp = "ab . cde.f";
+    let (names, _) = parse(par, inp).unwrap();
+
+    let spans: Vec<Span> = names.iter().map(|(_, span)| *span).collect();
+    assert_eq!(
+        vec![
+            Span { start: 0, end: 2 },
+            Span { start: 5, end: 8 },
+            Span { start: 9, end: 10 },
+        ],
+        spans
+    );
+    assert_eq!((1, 10), line_col(inp, spans[2].start));
+
+    // on its own, the spanned parser counts from its input
+    let ((_, span), _) = reg(r"[a-z]+").spanned().parse(&inp[5..]).unwrap();
+    assert_eq!(Span { start: 0, end: 3 }, span);
+
+    // so does a spanned parser inside a grammar run without `parse`
+    let par = lit("let ").and_r(reg(r"\w+").spanned());
+    let ((_, span), _) = par.parse("let abc").unwrap();
+    assert_eq!(Span { start: 0, end: 3 }, span);
+    let ((_, span), _) = parse(par, "let abc").unwrap();
+    assert_eq!(Span { start: 4, end: 7 }, span);
+}
+
+fn one(input: &[u8]) -> ParseResult<&[u8], u8> {
+    match input.split_first() {
+        Some((1, rest)) => Ok((1, rest)),
+        _ => Err(ParseError::expected(input, "1")),
+    }
+}
+
+#[test]
+fn test_spanned_slice_input() {
+    let par = one.and(one).spanned();
+
+    let inp: &[u8] = &[1, 1, 2];
+    let (ones, rest) = par.parse(inp).unwrap();
+    assert_eq!(((1, 1), Span { start: 0, end: 2 }), ones);
+    assert_eq!(&[2], rest);
+}
+
+#[test]
+fn test_try_map_error_position() {
+    let byte = reg(r"\d+").try_map(|s| s.parse::<u8>());
+    let par = byte
+        .and(lit("."))
+        .and(reg(r"\d+").try_map(|s| s.parse::<u8>()));
+
+    let err = parse(par, "12.345").unwrap_err();
+    assert_eq!(3, err.offset);
+    assert_eq!("3", err.found);
+    assert!(err.expected.is_empty());
+    assert_eq!(
+        "1:4: number too large to fit in target type",
+        err.to_string()
+    );
+}
+
+#[derive(Debug, PartialEq)]
+enum ConfigError {
+    Syntax(usize),
+}
+
+#[test]
+fn test_map_err() {
+    let par = lit("key")
+        .and(lit("="))
+        .map_err(|e| ConfigError::Syntax(e.expected.len()));
+    assert_eq!(Err(ConfigError::Syntax(1)), par.parse("key:"));
+
+    let entry = lit("key").map_err(|mut e: ParseError| {
+        e.expected = vec!["entry".to_string()];
+        e
+    });
+    let err = parse(lit("#").and(entry), "#x").unwrap_err();
+    assert_eq!(1, err.offset);
+    assert_eq!(vec!["entry"], err.expected);
+}
+
+#[test]
+fn test_map_err_keeps_position() {
+    let inp = "key =\n  x";
+    let par = lit("key")
+        .and_l(lit_sp("="))
+        .and(lit("value"))
+        .map_err(|e| e.remaining);
+
+    let remaining = par.parse(inp).unwrap_err();
+    assert_eq!((2, 3), line_col(inp, inp.len() - remaining));
+}
+
+#[test]
+fn test_flat_map_heredoc() {
+    let tag = lit("<<").and_r(reg(r"\w+")).and_l(lit("\n"));
+    let heredoc = tag.flat_map(|t| {
+        reg(&format!(r"(?s).*?\n{t}\n")).map(move |s| s[..s.len() - t.len() - 2].to_string())
+    });
+    let par = heredoc.and(reg(".*"));
+
+    let inp = "<<EOT\nline 1\nEOT is not the end\nEOT\nrest";
+    let res = parse(par, inp);
+    assert_eq!(
+        Ok((
+            ("line 1\nEOT is not the end".to_string(), "rest".to_string()),
+            ""
+        )),
+        res
+    );
+
+    // the failure of the chosen parser is reported at its position
+    let tag = lit("<<").and_r(reg(r"\w+")).and_l(lit("\n"));
+    let err = parse(tag.flat_map(|t| lit(&t)), "<<A\nB").unwrap_err();
+    assert_eq!(4, err.offset);
+    assert_eq!(vec!["`A`"], err.expected);
+}
+
+#[test]
+fn test_keyword_with_not() {
+    let keyword = |k: &str| lit(k).and_l(not(reg(r"[\w]")));
+    let par = keyword("null").or(reg(r"\w+")).rc();
+
+    assert_eq!(Ok(("null".to_string(), " x")), parse(par.clone(), "null x"));
+    assert_eq!(
+        Ok(("nullable".to_string(), "")),
+        parse(par.clone(), "nullable")
+    );
+
+    let err = parse(keyword("null"), "nulls").unwrap_err();
+    assert_eq!(4, err.offset);
+    assert_eq!("1:5: unexpected `s`", err.to_string());
+}
+
+#[test]
+fn test_not_passes_fatal_errors() {
+    let par = not(lit("a").and(lit("b").cut())).and(reg(r"\w+"));
+
+    assert!(parse(par, "xy").is_ok());
+
+    let par = not(lit("a").and(lit("b").cut()));
+    let err = parse(par, "ax").unwrap_err();
+    assert!(err.fatal);
+    assert_eq!(1, err.offset);
+    assert_eq!(vec!["`b`"], err.expected);
+}
+
+#[test]
+fn test_lookahead_failures_are_not_reported() {
+    let ab = || lit("a").and(lit("b").repeat()).and(lit("c"));
+
+    let err = parse(not(ab()).and(lit("x")), "abbz").unwrap_err();
+    assert_eq!(0, err.offset);
+    assert_eq!(vec!["`x`"], err.expected);
+
+    let err = parse(peek(ab().opt()).and(lit("x")), "abbz").unwrap_err();
+    assert_eq!(0, err.offset);
+    assert_eq!(vec!["`x`"], err.expected);
+
+    // failures recorded before the lookahead are kept
+    let par = lit("a").repeat().and(not(ab())).and(lit("x"));
+    let err = parse(par, "aab").unwrap_err();
+    assert_eq!(2, err.offset);
+    assert_eq!(vec!["`a`", "`x`"], err.expected);
+}
+
+#[test]
+fn test_peek_and_eof() {
+    let par = peek(lit("a")).and(reg(r"\w+")).and_l(eof()).rc();
+
+    assert_eq!(
+        Ok((("a".to_string(), "abc".to_string()), "")),
+        parse(par.clone(), "abc")
+    );
+
+    let err = parse(par.clone(), "abc d").unwrap_err();
+    assert_eq!(3, err.offset);
+    assert_eq!("1:4: expected end of input, found ` `", err.to_string());
+
+    let err = parse(par.clone(), "bcd").unwrap_err();
+    assert_eq!(0, err.offset);
+    assert_eq!(vec!["`a`"], err.expected);
+}
+
+fn expr_p(input: &str) -> ParseResult<&str, String> {
+    let paren = lit_sp("(").and_r(expr_p).and_l(lit_sp(")"));
+    let atom = reg(r"\w+").or(paren);
+
+    let par = pratt(atom)
+        .prefix(5, lit_sp("-"), |op, x| format!("({op}{x})"))
+        .infix(Assoc::Left, 1, lit_sp("+").or(lit_sp("-")), |op, x, y| {
+            format!("({x}{op}{y})")
+        })
+        .infix(Assoc::Left, 2, lit_sp("*"), |op, x, y| {
+            format!("({x}{op}{y})")
+        })
+        .infix(Assoc::Right, 3, lit_sp("^"), |op, x, y| {
+            format!("({x}{op}{y})")
+        })
+        .postfix(4, lit_sp("!"), |op, x| format!("({x}{op})"))
+        .postfix(6, lit_sp("?"), |op, x| format!("({x}{op})"));
+
+    par.parse(input)
+}
+
+#[test]
+fn test_pratt_precedence_and_assoc() {
+    let cases = [
+        ("a", "a"),
+        ("a + b - c", "((a+b)-c)"),
+        ("a + b * c", "(a+(b*c))"),
+        ("a * b + c", "((a*b)+c)"),
+        ("a ^ b ^ c", "(a^(b^c))"),
+        ("a * b ^ c ^ d * e", "((a*(b^(c^d)))*e)"),
+        ("(a + b) * c", "((a+b)*c)"),
+        ("- a * b", "((-a)*b)"),
+        ("- - a", "(-(-a))"),
+        ("a ^ b !", "(a^(b!))"),
+        ("a * b ? ^ c", "(a*((b?)^c))"),
+        ("- a !", "((-a)!)"),
+        ("- a ?", "(-(a?))"),
+        ("a ! ! + b", "(((a!)!)+b)"),
+    ];
+
+    for (inp, out) in cases {
+        assert_eq!(Ok((out.to_string(), "")), parse(expr_p, inp), "{}", inp);
+    }
+}
+
+#[test]
+fn test_pratt_errors() {
+    let err = parse(expr_p, "a + * b").unwrap_err();
+    assert_eq!(4, err.offset);
+    assert_eq!(vec!["`-`", r"/\w+/", "`(`"], err.expected);
+
+    let err = parse(expr_p.and_l(eof()), "a + b)").unwrap_err();
+    assert_eq!(5, err.offset);
+    assert_eq!(
+        vec!["`!`", "`?`", "`+`", "`-`", "`*`", "`^`", "end of input"],
+        err.expected
+    );
+}
+
+#[test]
+fn test_pratt_max_precedence() {
+    let num = || reg(r"\d+").map(|s| s.parse::<i64>().unwrap());
+    let par = pratt(num())
+        .prefix(u32::MAX, lit_sp("-"), |_, x| -x)
+        .infix(Assoc::Left, u32::MAX, lit_sp("-"), |_, x, y| x - y)
+        .infix(Assoc::Right, u32::MAX - 1, lit_sp("^"), |_, x, y| {
+            x.pow(y as u32)
+        })
+        .postfix(u32::MAX, lit_sp("!"), |_, x| (1..=x).product());
+
+    assert_eq!(Ok((-4, "")), par.parse("1 - 2 - 3"));
+    assert_eq!(Ok((2, "")), par.parse("2 ^ 3 ^ 0"));
+    assert_eq!(Ok((4, "")), par.parse("- 2 ! ^ 2"));
+}
+
+fn sum_p(input: &str) -> ParseResult<&str, i64> {
+    let num = reg(r"\d+").map(|s| s.parse::<i64>().unwrap());
+    let term = num.or(lit_sp("(").and_r(sum_p).and_l(lit_sp(")")));
+    let add = lit_sp("+").map(|_| (|x, y| x + y) as fn(i64, i64) -> i64);
+    let sub = lit_sp("-").map(|_| (|x, y| x - y) as fn(i64, i64) -> i64);
+
+    chainl1(term, add.or(sub)).parse(input)
+}
+
+#[test]
+fn test_chainl1() {
+    assert_eq!(Ok((7, "")), parse(sum_p, "7"));
+    assert_eq!(Ok((-4, "")), parse(sum_p, "1 - 2 - 3"));
+    assert_eq!(Ok((2, "")), parse(sum_p, "1 - (2 - 3)"));
+    assert_eq!(Ok((6, "")), parse(sum_p, "10 - 5 + 1"));
+
+    // a trailing operator is left to the caller
+    assert_eq!(Ok((3, " + )")), parse(sum_p, "1 + 2 + )"));
+
+    let err = parse(sum_p.and_l(eof()), "1 + 2 + )").unwrap_err();
+    assert_eq!(8, err.offset);
+    assert_eq!(vec![r"/\d+/", "`(`"], err.expected);
+}
+
+#[test]
+fn test_chainr1() {
+    let atom = reg(r"\w").map(|s| s.to_string());
+    let arrow = lit_sp("->").map(|_| |x, y| format!("({x}->{y})"));
+    let par = chainr1(atom, arrow).rc();
+
+    assert_eq!(Ok(("a".to_string(), "")), parse(par.clone(), "a"));
+    assert_eq!(
+        Ok(("(a->(b->c))".to_string(), "")),
+        parse(par.clone(), "a -> b -> c")
+    );
+    assert_eq!(
+        Ok(("(a->b)".to_string(), " ->")),
+        parse(par.clone(), "a -> b ->")
+    );
+}
+
+thread_local! {
+    static CALLS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+fn counted_a(input: &str) -> ParseResult<&str, String> {
+    CALLS.set(CALLS.get() + 1);
+    lit_sp("a").parse(input)
+}
+
+#[test]
+fn test_memo_runs_once_per_offset() {
+    let item = counted_a.memo("a").rc();
+    let par = item
+        .clone()
+        .and(lit("1"))
+        .or(item.clone().and(lit("2")))
+        .or(item.clone().and(lit("3")))
+        .repeat();
+
+    CALLS.set(0);
+    let res = parse(par, " a3 a1a2 a?").unwrap();
+    assert_eq!(3, res.0.len());
+    assert_eq!(4, CALLS.get());
+
+    // memoized failures are replayed as well
+    CALLS.set(0);
+    let par = item.clone().and(lit("1")).or(item.clone().and(lit("2")));
+    let err = parse(par, "b").unwrap_err();
+    assert_eq!(vec!["`a`"], err.expected);
+    assert_eq!(1, CALLS.get());
+
+    // without a session, there is nothing to cache in
+    CALLS.set(0);
+    let par = item.clone().and(lit("1")).or(item.and(lit("2")));
+    assert!(par.parse("a2").is_ok());
+    assert_eq!(2, CALLS.get());
+}
+
+// rebuilds its memoized parser on every call
+fn memo_a_p(input: &str) -> ParseResult<&str, String> {
+    counted_a.memo("a").parse(input)
+}
+
+#[test]
+fn test_memo_in_fn_item() {
+    let par = memo_a_p
+        .and(lit("1"))
+        .or(memo_a_p.and(lit("2")))
+        .or(memo_a_p.and(lit("3")));
+
+    CALLS.set(0);
+    assert!(parse(par, "a3").is_ok());
+    assert_eq!(1, CALLS.get());
+}
+
+// sum = sum ("+" | "-") prod | prod
+fn lr_sum_p(input: &str) -> ParseResult<&str, String> {
+    let op = lit_sp("+").or(lit_sp("-"));
+    let sum = lr_sum_p
+        .and(op)
+        .and(lr_prod_p)
+        .map(|((x, op), y)| format!("({x}{op}{y})"));
+
+    sum.or(lr_prod_p).left_rec("sum").parse(input)
+}
+
+// prod = prod "*" atom | atom
+fn lr_prod_p(input: &str) -> ParseResult<&str, String> {
+    let atom = || reg(r"\w+").or(lit_sp("(").and_r(lr_sum_p).and_l(lit_sp(")")));
+    let prod = lr_prod_p
+        .and(lit_sp("*"))
+        .and(atom())
+        .map(|((x, op), y)| format!("({x}{op}{y})"));
+
+    prod.or(atom()).left_rec("prod").parse(input)
+}
+
+#[test]
+fn test_left_rec() {
+    let cases = [
+        ("a", "a"),
+        ("a - b - c", "((a-b)-c)"),
+        ("a + b * c * d - e", "((a+((b*c)*d))-e)"),
+        ("a * (b - c - d)", "(a*((b-c)-d))"),
+    ];
+
+    for (inp, out) in cases {
+        assert_eq!(Ok((out.to_string(), "")), parse(lr_sum_p, inp), "{}", inp);
+    }
+
+    // also without a top-level session
+    assert_eq!(
+        Ok(("((a-b)-c)".to_string(), " )")),
+        lr_sum_p.parse("a - b - c )")
+    );
+
+    let err = parse(lr_sum_p.and_l(eof()), "a - (b -)").unwrap_err();
+    assert_eq!(8, err.offset);
+    assert_eq!(vec![r"/\w+/", "`(`"], err.expected);
+}
+
+// a = b "x" | "y", b = a "z"
+fn lr_a_p(input: &str) -> ParseResult<&str, String> {
+    let bx = lr_b_p.and(lit("x")).map(|(b, x)| b + &x);
+
+    bx.or(lit("y")).left_rec("a").parse(input)
+}
+
+fn lr_b_p(input: &str) -> ParseResult<&str, String> {
+    lr_a_p.and(lit("z")).map(|(a, z)| a + &z).parse(input)
+}
+
+#[test]
+fn test_left_rec_indirect() {
+    assert_eq!(Ok(("y".to_string(), "")), parse(lr_a_p, "y"));
+    assert_eq!(Ok(("yzxzx".to_string(), "z")), parse(lr_a_p, "yzxzxz"));
+}
+
+type Rule = BoxedParser<'static, str, String, ParseError>;
+
+// sum = sum "+" prod | prod, prod = prod "*" digit | digit, both of type `Rule`
+fn boxed_sum_p(input: &str) -> ParseResult<&str, String> {
+    let sum: Rule = boxed_sum_p
+        .and_l(lit("+"))
+        .and(boxed_prod_p)
+        .map(|(x, y)| format!("({x}+{y})"))
+        .or(boxed_prod_p)
+        .boxed();
+
+    sum.left_rec("sum").parse(input)
+}
+
+fn boxed_prod_p(input: &str) -> ParseResult<&str, String> {
+    let prod: Rule = boxed_prod_p
+        .and_l(lit("*"))
+        .and(reg(r"\d"))
+        .map(|(x, y)| format!("({x}*{y})"))
+        .or(reg(r"\d"))
+        .boxed();
+
+    prod.left_rec("prod").parse(input)
+}
+
+#[test]
+fn test_left_rec_rules_of_same_type() {
+    assert_eq!(
+        Ok(("(1+(2*3))".to_string(), "")),
+        parse(boxed_sum_p, "1+2*3")
+    );
+    assert_eq!(
+        Ok(("((1*2)+3)".to_string(), "")),
+        parse(boxed_sum_p, "1*2+3")
+    );
+}
+
+#[derive(Debug, PartialEq)]
+enum Tree {
+    Leaf(String),
+    Node(Vec<Tree>),
+}
+
+#[test]
+fn test_recursive() {
+    // tree = word | "[" tree* "]", with the brackets picked at runtime
+    let (open, close) = ("<".to_string(), ">".to_string());
+    let tree = recursive(|tree| {
+        let node = lit_sp(&open)
+            .and_r(tree.repeat())
+            .and_l(lit_sp(&close))
+            .map(Tree::Node);
+
+        reg(r"\w+").and_l(spaces()).map(Tree::Leaf).or(node)
+    });
+
+    let leaf = |s: &str| Tree::Leaf(s.to_string());
+    let expected = Tree::Node(vec![
+        leaf("a"),
+        Tree::Node(vec![leaf("b"), Tree::Node(vec![])]),
+        leaf("c"),
+    ]);
+    assert_eq!(Ok((expected, "")), parse(tree.clone(), "< a < b <> > c >"));
+
+    let err = parse(tree, "< a < b >").unwrap_err();
+    assert_eq!(9, err.offset);
+}
+
+#[test]
+fn test_recursive_drop() {
+    let marker = Rc::new(());
+    let inner = marker.clone();
+    let nested = recursive(|nested| {
+        lit("(")
+            .and_r(nested.opt())
+            .and_l(lit(")"))
+            .map(move |_| drop(inner.clone()))
+    });
+
+    assert_eq!(Ok(((), "")), nested.parse("(())"));
+    assert_eq!(2, Rc::strong_count(&marker));
+
+    drop(nested);
+    assert_eq!(1, Rc::strong_count(&marker));
+}
+
+#[test]
+fn test_boxed_keyword_table() {
+    let keywords = ["if", "else", "while"];
+    let keyword = keywords
+        .iter()
+        .map(|k| lit(k).and_l(not(reg(r"\w"))).boxed())
+        .reduce(|acc, p| acc.or(p).boxed())
+        .unwrap();
+
+    let mut table: Vec<BoxedParser<str, String, ParseError>> = vec![keyword];
+    table.push(reg(r"\d+").boxed());
+    table.push(lit_sp("(").and_r(reg(r"\w+")).and_l(lit(")")).boxed());
+
+    let tokenize = |inp| {
+        table
+            .iter()
+            .find_map(|p| p.parse(inp).ok())
+            .map(|(tok, _)| tok)
+    };
+    assert_eq!(Some("while".to_string()), tokenize("while"));
+    assert_eq!(None, tokenize("whiles"));
+    assert_eq!(Some("42".to_string()), tokenize("42"));
+    assert_eq!(Some("x".to_string()), tokenize("( x)"));
+
+    let par: &dyn Parser<'_, str, String, ParseError> = &table[1];
+    assert!(par.parse("x").is_err());
+}
+
+struct Grammar {
+    keyword: BoxedParser<'static, str, String, ParseError>,
+    nested: RecursiveParser<'static, str, usize, ParseError>,
+}
+
+#[test]
+fn test_boxed_grammar_outlives_inputs() {
+    let grammar = Grammar {
+        keyword: lit("let").boxed(),
+        nested: recursive(|nested| {
+            lit("(")
+                .and_r(nested.repeat())
+                .and_l(lit(")"))
+                .map(|inner| inner.iter().sum::<usize>() + 1)
+        }),
+    };
+
+    // the inputs are allocated after the grammar, and dropped before it
+    for i in 1..4 {
+        let owned = format!("let{}", "()".repeat(i));
+        let (_, rest) = grammar.keyword.parse(&owned).unwrap();
+        assert_eq!(
+            Ok((1, "()".repeat(i - 1).as_str())),
+            grammar.nested.parse(rest)
+        );
+    }
+}
+
+fn assert_send_sync<T: Send + Sync>(t: T) -> T {
+    t
+}
+
+type Assignment = AndParser<AndLeftParser<RegexParser, LiteralParser, String>, RegexParser>;
+
+static ASSIGNMENT: OnceLock<ArcParser<Assignment>> = OnceLock::new();
+
+fn assignment() -> ArcParser<Assignment> {
+    ASSIGNMENT
+        .get_or_init(|| reg(r"\w+").and_l(lit("=")).and(reg(r"\d+")).arc())
+        .clone()
+}
+
+#[test]
+fn test_arc_parser_across_threads() {
+    let par = assert_send_sync(
+        assignment()
+            .map(|(k, v)| (k, v.parse::<u32>().unwrap()))
+            .or(lit("-").map(|_| ("none".to_string(), 0)))
+            .spanned()
+            .cut()
+            .arc(),
+    );
+
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            let par = par.clone();
+            thread::spawn(move || parse(par, &format!("x{i}={i}")).map(|((kv, _), _)| kv))
+        })
+        .collect();
+
+    for (i, handle) in handles.into_iter().enumerate() {
+        assert_eq!(Ok((format!("x{i}"), i as u32)), handle.join().unwrap());
+    }
+}
+
+crate::lazy_parser! {
+    // calc = calc ("+" | "*") calc | num, with `*` binding tighter
+    fn calc_p(&str) -> i64 = pratt(reg(r"\d+").map(|s| s.parse::<i64>().unwrap()))
+        .infix(Assoc::Left, 1, lit_sp("+"), |_, x, y| x + y)
+        .infix(Assoc::Left, 2, lit_sp("*"), |_, x, y| x * y);
+}
+
+#[test]
+fn test_pratt_parser_across_threads() {
+    let num = reg(r"\d+").map(|s| s.parse::<i64>().unwrap());
+    let par = pratt(num)
+        .prefix(3, lit_sp("-"), |_, x| -x)
+        .infix(Assoc::Left, 1, lit_sp("-"), |_, x, y| x - y)
+        .postfix(2, lit_sp("!"), |_, x| (1..=x).product());
+    let par = assert_send_sync(par).arc();
+
+    let handles: Vec<_> = (1..5)
+        .map(|i| {
+            let par = par.clone();
+            thread::spawn(move || parse(par, &format!("{i}! - -1")).map(|(x, _)| x))
+        })
+        .collect();
+    for (handle, fact) in handles.into_iter().zip([1, 2, 6, 24]) {
+        assert_eq!(Ok(fact + 1), handle.join().unwrap());
+    }
+
+    assert_eq!(Ok((7, "")), parse(calc_p, "1 + 2 * 3"));
+}
+
+crate::lazy_parser! {
+    // value = num | "[" value ("," value)* "]", summing the numbers
+    fn nested_sum_p(&str) -> u32 = recursive_sync(|value| {
+        let list = lit("[").and_r(lst_sep(value, lit(","))).and_l(lit("]"));
+
+        reg(r"\d+")
+            .map(|s| s.parse().unwrap())
+            .or(list.map(|xs| xs.iter().sum()))
+    });
+}
+
+#[test]
+fn test_sync_grammar_across_threads() {
+    let keywords = ["if", "else", "while"];
+    let keyword = keywords
+        .iter()
+        .map(|k| lit(k).and_l(not(reg(r"\w"))).boxed_sync())
+        .reduce(|acc, p| acc.or(p).boxed_sync())
+        .unwrap();
+    let stmt = assert_send_sync(keyword.and_l(spaces()).and(nested_sum_p)).arc();
+
+    let handles: Vec<_> = keywords
+        .iter()
+        .enumerate()
+        .map(|(i, k)| {
+            let stmt = stmt.clone();
+            let inp = format!("{k} [{i},[1,{i}]]");
+            thread::spawn(move || parse(stmt, &inp).map(|(out, _)| out))
+        })
+        .collect();
+    for ((i, k), handle) in keywords.iter().enumerate().zip(handles) {
+        assert_eq!(
+            Ok((k.to_string(), 2 * i as u32 + 1)),
+            handle.join().unwrap()
+        );
+    }
+
+    let nested = assert_send_sync(recursive_sync(|nested| {
+        lit("(").and_r(nested.opt()).and_l(lit(")")).map(|_| ())
+    }));
+    assert_eq!(Ok(((), "")), parse(nested, "((()))"));
+}
+
+static BUILDS: AtomicUsize = AtomicUsize::new(0);
+
+fn ident_parser() -> RegexParser {
+    BUILDS.fetch_add(1, Ordering::SeqCst);
+    reg(r"[a-z]+")
+}
+
+crate::lazy_parser! {
+    fn ident_p(&str) -> String = ident_parser();
+
+    fn idents_p(&str) -> Vec<String> = lst_sep(ident_p, lit(","));
+}
+
+#[test]
+fn test_lazy_parser_built_once() {
+    let handles: Vec<_> = (0..4)
+        .map(|_| thread::spawn(|| parse(idents_p, "a,bc,d").map(|(ids, _)| ids.len())))
+        .collect();
+    for handle in handles {
+        assert_eq!(Ok(3), handle.join().unwrap());
+    }
+
+    assert!(parse(idents_p.and_l(eof()), "a,1").is_err());
+    assert_eq!(1, BUILDS.load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_seq_flat_tuple() {
+    let digit = || reg(r"\d").map(|d| d.parse::<u8>().unwrap());
+    let par = seq((
+        digit(),
+        lit("-"),
+        digit(),
+        digit(),
+        lit("-"),
+        digit(),
+        digit(),
+        digit(),
+        lit("-"),
+        digit(),
+        digit(),
+        reg(r"[a-z]"),
+    ));
+
+    let ((a, _, b, c, _, d, e, f, _, g, h, x), rest) = par.parse("1-23-456-78z!").unwrap();
+    assert_eq!([1, 2, 3, 4, 5, 6, 7, 8], [a, b, c, d, e, f, g, h]);
+    assert_eq!(("z", "!"), (x.as_str(), rest));
+
+    let err = parse(par, "1-23-456-7z").unwrap_err();
+    assert_eq!(10, err.offset);
+    assert_eq!(vec![r"/\d/"], err.expected);
+}
+
+#[test]
+fn test_alt() {
+    let par = alt((
+        lit("(").and_r(lit(")").cut()),
+        lit("[").and_l(lit("]")),
+        lit("{").and_l(lit("}")),
+    ))
+    .rc();
+
+    assert_eq!(Ok(("{".to_string(), "")), parse(par.clone(), "{}"));
+
+    // the error of the second alternative got further
+    let err = parse(alt((lit("a"), lit("[").and_l(lit("]")), lit("b"))), "[x").unwrap_err();
+    assert_eq!(1, err.offset);
+    assert_eq!(vec!["`]`"], err.expected);
+
+    // fatal errors are not recovered from
+    let err = parse(par, "(]").unwrap_err();
+    assert!(err.fatal);
+    assert_eq!(vec!["`)`"], err.expected);
+}
+
+#[test]
+fn test_repeat_range_bounds() {
+    let a = || lit("a");
+    let lens = |par: RepeatRangeParser<LiteralParser>| {
+        parse(par, "aaaaa").map(|(res, rest)| (res.len(), rest.len()))
+    };
+
+    assert_eq!(Ok((5, 0)), lens(a().repeat_range(..)));
+    assert_eq!(Ok((3, 2)), lens(a().repeat_range(..=3)));
+    assert_eq!(Ok((2, 3)), lens(a().repeat_range(1..3)));
+    assert_eq!(Ok((5, 0)), lens(a().repeat_range(5..)));
+    assert_eq!(Ok((0, 5)), lens(a().repeat_n(0)));
+    assert_eq!(Ok((4, 1)), lens(count(a(), 4)));
+
+    let err = lens(a().repeat_range(6..8)).unwrap_err();
+    assert_eq!(5, err.offset);
+    assert_eq!(
+        Some("expected at least 6 repetitions, found 5"),
+        err.message()
+    );
+    assert_eq!(vec!["`a`"], err.expected);
+    assert!(err.context().is_empty());
+
+    // the last repetition failed past its start, its error is kept as is
+    let ab = lit("a").and(lit("b")).repeat_n(2);
+    let err = parse(ab, "aba").unwrap_err();
+    assert_eq!((3, None), (err.offset, err.message()));
+    assert_eq!(vec!["`b`"], err.expected);
+}
+
+#[test]
+#[should_panic(expected = "empty repetition range")]
+fn test_repeat_range_empty() {
+    let _ = lit("a").repeat_range(3..3);
+}
+
+#[test]
+fn test_repeat_range_ip_address() {
+    let octet = || {
+        reg(r"\d")
+            .repeat_range(1..=3)
+            .try_map(|ds| ds.concat().parse::<u8>())
+    };
+    let ip = seq((octet(), lit(".").and_r(octet()).repeat_n(3).cut(), eof()))
+        .map(|(a, rest, _)| [a, rest[0], rest[1], rest[2]]);
+    let ip = ip.rc();
+
+    assert_eq!(Ok(([192, 168, 0, 1], "")), parse(ip.clone(), "192.168.0.1"));
+
+    let err = parse(ip.clone(), "10.0.1").unwrap_err();
+    assert!(err.fatal);
+    assert_eq!(6, err.offset);
+    assert_eq!(vec![r"/\d/", "`.`"], err.expected);
+
+    let err = parse(ip.clone(), "1.2.3.1234").unwrap_err();
+    assert_eq!(9, err.offset);
+    assert_eq!(vec!["end of input"], err.expected);
+
+    let err = parse(ip, "1.2.3.456").unwrap_err();
+    assert_eq!(6, err.offset);
+    assert_eq!(
+        Some("number too large to fit in target type"),
+        err.message()
+    );
+}
+
+#[test]
+fn test_recognize() {
+    let ident = || reg(r"[a-z]+");
+    let path = seq((ident(), lit("::").and_r(ident()).repeat()))
+        .recognize()
+        .and_l(lit("("));
+
+    let inp = String::from("std::mem::take(x)");
+    let (name, rest) = path.parse(&inp).unwrap();
+    assert_eq!(("std::mem::take", "x)"), (name, rest));
+    assert_eq!(inp.as_ptr(), name.as_ptr());
+
+    // slice inputs
+    let inp: &[u8] = &[1, 1, 2];
+    let par = one.and(one).with_consumed();
+    assert_eq!(Ok((((1, 1), &inp[..2]), &inp[2..])), par.parse(inp));
+    assert!(par.parse(&inp[1..]).is_err());
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Num(i64),
+    Plus,
+}
+
+fn binop(toks: &[Token]) -> ParseResult<&[Token], &Token> {
+    let num = || satisfy(|t: &&Token| matches!(t, Token::Num(_)));
+
+    num().and_r(any()).and_l(num()).parse(toks)
+}
+
+#[test]
+fn test_token_stream() {
+    let toks = [Token::Num(1), Token::Plus, Token::Num(2)];
+    assert_eq!(Ok((&Token::Plus, &toks[3..])), binop(&toks));
+
+    let err = parse(binop, &toks[1..]).unwrap_err();
+    assert_eq!((0, "Plus"), (err.offset, err.found.as_str()));
+
+    let err = parse(binop, &toks[..2]).unwrap_err();
+    assert_eq!((2, 3), (err.offset, err.column));
+    assert_eq!("", err.found);
+
+    let res: ParseResult<_, _> = parse(take(2).and_l(eof()), &toks[1..]);
+    assert_eq!(Ok((&toks[1..], &toks[3..])), res);
+}
+
+#[test]
+fn test_generic_primitives_on_text() {
+    let ident = satisfy(|c: &char| c.is_alphabetic())
+        .and(take_while(|c: &char| c.is_alphanumeric()))
+        .recognize();
+    let par = ident.and_l(take(1)).and(any()).rc();
+
+    let res: ParseResult<_, _> = parse(par.clone(), "héllo2 x");
+    assert_eq!(Ok((("héllo2", 'x'), "")), res);
+
+    let err: ParseError = parse(par, "2x").unwrap_err();
+    assert_eq!((0, "2"), (err.offset, err.found.as_str()));
 }
@@ -0,0 +1,147 @@
+use crate::parser::ParseError;
+use std::fmt;
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders a `ParseError` against the input it was produced from.
+///
+/// The error must have been resolved against `input`, as done by
+/// [`parse`](crate::parser::parse).
+///
+/// # Example:
+/// ```
+/// use parcomb::parser::{parse, Parser};
+/// use parcomb::report::Report;
+/// use parcomb::string_parser::lit;
+///
+/// let inp = "abd";
+/// let err = parse(lit("ab").and(lit("c")), inp).unwrap_err();
+///
+/// let out = Report::new(inp, &err).file("test.txt").render();
+/// let expected = [
+///     "error: unexpected `d`",
+///     " --> test.txt:1:3",
+///     "  |",
+///     "1 | abd",
+///     "  |   ^",
+///     "  = expected `c`",
+/// ];
+/// assert_eq!(expected.join("\n") + "\n", out);
+/// ```
+pub struct Report<'a> {
+    input: &'a str,
+    error: &'a ParseError,
+    file: Option<&'a str>,
+    color: bool,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(input: &'a str, error: &'a ParseError) -> Self {
+        Report {
+            input,
+            error,
+            file: None,
+            color: false,
+        }
+    }
+
+    /// Sets the file name shown next to the error position.
+    pub fn file(mut self, file: &'a str) -> Self {
+        self.file = Some(file);
+        self
+    }
+
+    /// Turns ANSI colors on or off, off by default.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn render(&self) -> String {
+        self.to_string()
+    }
+
+    /// Returns `code` if colors are on, an empty string otherwise.
+    fn paint(&self, code: &'static str) -> &'static str {
+        if self.color {
+            code
+        } else {
+            ""
+        }
+    }
+}
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (red, blue, bold, reset) = (
+            self.paint(RED),
+            self.paint(BLUE),
+            self.paint(BOLD),
+            self.paint(RESET),
+        );
+        let err = self.error;
+
+        let message = match err.message() {
+            Some(message) => message.to_string(),
+            None if err.found.is_empty() => "unexpected end of input".to_string(),
+            None => format!("unexpected `{}`", err.found),
+        };
+        writeln!(f, "{red}error{reset}{bold}: {message}{reset}")?;
+
+        let gutter = " ".repeat(err.line.to_string().len());
+        match self.file {
+            Some(file) => writeln!(
+                f,
+                "{gutter}{blue}-->{reset} {file}:{}:{}",
+                err.line, err.column
+            )?,
+            None => writeln!(f, "{gutter}{blue}-->{reset} {}:{}", err.line, err.column)?,
+        }
+
+        let line = self
+            .input
+            .split('\n')
+            .nth(err.line - 1)
+            .unwrap_or_default()
+            .trim_end_matches('\r');
+        let before: String = line.chars().take(err.column - 1).collect();
+        let underline = "^".repeat(err.found.chars().count().max(1));
+
+        writeln!(f, "{gutter} {blue}|{reset}")?;
+        writeln!(
+            f,
+            "{blue}{}{reset} {blue}|{reset} {}",
+            err.line,
+            expand_tabs(line)
+        )?;
+        writeln!(
+            f,
+            "{gutter} {blue}|{reset} {}{red}{underline}{reset}",
+            " ".repeat(expand_tabs(&before).chars().count())
+        )?;
+
+        match err.expected.len() {
+            0 => {}
+            1 => writeln!(f, "{gutter} {blue}={reset} expected {}", err.expected[0])?,
+            _ => writeln!(
+                f,
+                "{gutter} {blue}={reset} expected one of: {}",
+                err.expected.join(", ")
+            )?,
+        }
+
+        for context in err.context() {
+            writeln!(f, "{gutter} {blue}={reset} note: {context}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Replaces tabs with spaces, so that the underline lines up with the source.
+fn expand_tabs(s: &str) -> String {
+    s.replace('\t', "    ")
+}
@@ -0,0 +1,120 @@
+use super::parser::*;
+use super::report::Report;
+use super::string_parser::*;
+
+fn value_p(input: &str) -> ParseResult<&str, String> {
+    let item = lit("null").or(lit("true")).or(reg(r"\d+"));
+    let list = lit_sp("[")
+        .and_r(lst_sep_empt(item, lit_sp(",")))
+        .and_l(lit_sp("]"))
+        .map(|items| items.join(","));
+
+    list.parse(input)
+}
+
+#[test]
+fn test_render_plain() {
+    let inp = "[\n  1,\n\tnul, 2\n]";
+    let err = parse(value_p, inp).unwrap_err();
+
+    let out = Report::new(inp, &err).file("list.json").render();
+    let expected = r#"error: unexpected `n`
+ --> list.json:3:2
+  |
+3 |     nul, 2
+  |     ^
+  = expected one of: `null`, `true`, /\d+/
+"#;
+    assert_eq!(expected, out);
+}
+
+#[test]
+fn test_render_end_of_input() {
+    let inp = "[1, 2";
+    let err = parse(value_p, inp).unwrap_err();
+
+    let out = Report::new(inp, &err).render();
+    let expected = r#"error: unexpected end of input
+ --> 1:6
+  |
+1 | [1, 2
+  |      ^
+  = expected one of: `,`, `]`
+"#;
+    assert_eq!(expected, out);
+}
+
+#[test]
+fn test_render_wide_gutter() {
+    let inp = "[\n\n\n\n\n\n\n\n\n1 x]";
+    let err = parse(value_p, inp).unwrap_err();
+
+    let out = Report::new(inp, &err).render();
+    let expected = r#"error: unexpected `x`
+  --> 10:3
+   |
+10 | 1 x]
+   |   ^
+   = expected one of: `,`, `]`
+"#;
+    assert_eq!(expected, out);
+}
+
+#[test]
+fn test_render_color() {
+    let inp = "[x]";
+    let err = parse(value_p, inp).unwrap_err();
+
+    let out = Report::new(inp, &err).color(true).render();
+    let expected = "\x1b[1;31merror\x1b[0m\x1b[1m: unexpected `x`\x1b[0m\n \
+                    \x1b[1;34m-->\x1b[0m 1:2\n  \
+                    \x1b[1;34m|\x1b[0m\n\
+                    \x1b[1;34m1\x1b[0m \x1b[1;34m|\x1b[0m [x]\n  \
+                    \x1b[1;34m|\x1b[0m  \x1b[1;31m^\x1b[0m\n  \
+                    \x1b[1;34m=\x1b[0m expected one of: `null`, `true`, /\\d+/, `]`\n";
+    assert_eq!(expected, out);
+}
+
+#[test]
+fn test_render_context() {
+    let member = lit_sp("\"a\"")
+        .and(lit_sp(":"))
+        .and_r(value_p.label("list"));
+    let par = lit_sp("{")
+        .and_r(member.context("while parsing member"))
+        .and_l(lit_sp("}"))
+        .context("while parsing object");
+
+    let inp = "{ \"a\": [1, x] }";
+    let err = parse(par, inp).unwrap_err();
+
+    let out = Report::new(inp, &err).render();
+    let expected = r#"error: unexpected `x`
+ --> 1:12
+  |
+1 | { "a": [1, x] }
+  |            ^
+  = expected one of: `null`, `true`, /\d+/
+  = note: while parsing member
+  = note: while parsing object
+"#;
+    assert_eq!(expected, out);
+}
+
+#[test]
+fn test_render_message() {
+    let byte = reg(r"\d+").try_map(|s| s.parse::<u8>());
+    let par = lst_sep(byte, lit("."));
+
+    let inp = "10.0.300.1";
+    let err = parse(par.and(lit("!")), inp).unwrap_err();
+
+    let out = Report::new(inp, &err).render();
+    let expected = r#"error: number too large to fit in target type
+ --> 1:6
+  |
+1 | 10.0.300.1
+  |      ^
+"#;
+    assert_eq!(expected, out);
+}
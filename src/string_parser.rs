@@ -1,35 +1,58 @@
-use crate::parser::{ParseResult, Parser};
+use crate::parser::{ParseError, ParseResult, Parser};
 use regex::Regex;
 pub struct LiteralParser {
+    parser: LiteralStrParser,
+}
+
+impl<'a> Parser<'a, str, String, ParseError> for LiteralParser {
+    fn parse(&self, input: &'a str) -> ParseResult<&'a str, String> {
+        let (out, remain) = self.parser.parse(input)?;
+
+        Ok((out.to_string(), remain))
+    }
+}
+
+/// Like `LiteralParser`, but returns the matched slice of the input.
+pub struct LiteralStrParser {
     literal: String,
+    expected: String,
 }
 
-impl Parser<str, String, String> for LiteralParser {
-    fn parse<'a>(&self, input: &'a str) -> ParseResult<&'a str, String, String> {
+impl<'a> Parser<'a, str, &'a str, ParseError> for LiteralStrParser {
+    fn parse(&self, input: &'a str) -> ParseResult<&'a str, &'a str> {
         if input.starts_with(&self.literal) {
-            let remain = &input[self.literal.len()..];
-            let res = (self.literal.clone(), remain);
+            let res = input.split_at(self.literal.len());
 
             return Ok(res);
         }
 
-        return Err(format!("LiteralParser: No Match"));
+        Err(ParseError::new(input, &self.expected))
     }
 }
 
 pub struct RegexParser {
+    parser: RegexStrParser,
+}
+
+impl<'a> Parser<'a, str, String, ParseError> for RegexParser {
+    fn parse(&self, input: &'a str) -> ParseResult<&'a str, String> {
+        let (out, remain) = self.parser.parse(input)?;
+
+        Ok((out.to_string(), remain))
+    }
+}
+
+/// Like `RegexParser`, but returns the matched slice of the input.
+pub struct RegexStrParser {
     re: Regex,
+    expected: String,
 }
 
-impl Parser<str, String, String> for RegexParser {
-    fn parse<'a>(&self, input: &'a str) -> ParseResult<&'a str, String, String> {
+impl<'a> Parser<'a, str, &'a str, ParseError> for RegexStrParser {
+    fn parse(&self, input: &'a str) -> ParseResult<&'a str, &'a str> {
         match self.re.find(input) {
-            None => Err(format!("RegexParser: No Match")),
-            Some(mat) => {
-                let out = mat.as_str().to_string();
-                let remain_i = &input[(mat.end()..)];
-                Ok((out, remain_i))
-            }
+            None => Err(ParseError::new(input, &self.expected)),
+            Some(mat) => Ok(input.split_at(mat.end())),
         }
     }
 }
@@ -45,8 +68,30 @@ impl Parser<str, String, String> for RegexParser {
 /// assert_eq!(("hello".to_string(), ",world"), res);
 ///```
 pub fn lit(s: &str) -> LiteralParser {
-    LiteralParser {
+    LiteralParser { parser: lit_str(s) }
+}
+
+/// Matches the literal `s` without allocating, the output borrows from the
+/// input.
+/// Being borrowed, it can't be memoized with [`Parser::memo`], use
+/// [`Parser::memo_slice`] instead.
+///
+/// # Example:
+/// ```
+/// use parcomb::string_parser::*;
+/// use parcomb::parser::{Parser, ParseResult};
+///
+/// fn key_value(input: &str) -> ParseResult<&str, (&str, &str)> {
+///     reg_str(r"\w+").and_l(lit_str("=")).and(reg_str(r"\w*")).parse(input)
+/// }
+///
+/// let inp = String::from("key=value;");
+/// assert_eq!((("key", "value"), ";"), key_value(&inp).unwrap());
+///```
+pub fn lit_str(s: &str) -> LiteralStrParser {
+    LiteralStrParser {
         literal: s.to_string(),
+        expected: format!("`{}`", s),
     }
 }
 
@@ -62,13 +107,23 @@ pub fn lit(s: &str) -> LiteralParser {
 ///
 /// let inp2 = "ccc19abcd$$";
 /// let res2 = par.parse(inp2);
-/// assert!(res2.is_err());
+/// assert_eq!(vec![r"/\d{2}\w+/"], res2.unwrap_err().expected);
 /// ```
 pub fn reg(re: &str) -> RegexParser {
-    let re_pattern = format!("^{}", re);
+    RegexParser {
+        parser: reg_str(re),
+    }
+}
+
+/// Matches the regex `re` without allocating, the output borrows from the
+/// input.
+/// As with [`lit_str`], memoize it with [`Parser::memo_slice`].
+pub fn reg_str(re: &str) -> RegexStrParser {
+    let re_pattern = format!("^(?:{})", re);
+    let expected = format!("/{}/", re);
     let re = Regex::new(&re_pattern).unwrap();
 
-    RegexParser { re }
+    RegexStrParser { re, expected }
 }
 
 pub fn spaces() -> RegexParser {
@@ -85,6 +140,6 @@ pub fn spaces() -> RegexParser {
 /// let res = par.parse(inp).unwrap();
 /// assert_eq!(("{".to_string(), "ok }"), res);
 ///```
-pub fn lit_sp(s: &str) -> impl Parser<str, String, String> {
+pub fn lit_sp(s: &str) -> impl for<'a> Parser<'a, str, String, ParseError> {
     spaces().and_r(lit(s)).and_l(spaces())
 }
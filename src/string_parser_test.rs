@@ -1,5 +1,6 @@
-use super::parser::{ParseResult, Parser};
+use super::parser::{parse, recursive_ref, seq, BoxedRefParser, ParseError, ParseResult, Parser};
 use super::string_parser::*;
+use std::cell::Cell;
 use std::str::FromStr;
 
 #[test]
@@ -140,3 +141,84 @@ fn test_reg_parse_json_string() {
 
     //    println!("{:#?}", res);
 }
+
+#[test]
+fn test_reg_alternation_is_anchored() {
+    let par = reg_str("a|b");
+
+    assert_eq!(Ok(("b", " rest")), par.parse("b rest"));
+    assert!(par.parse("xxxb rest").is_err());
+    assert!(reg("a|b").parse("xxxa").is_err());
+}
+
+// a log line like `2023-01-02 WARN disk almost full`
+fn log_line_p(input: &str) -> ParseResult<&str, (&str, &str, &str)> {
+    let date = reg_str(r"\d{4}-\d{2}-\d{2}");
+    let level = lit_str("INFO").or(lit_str("WARN")).or(lit_str("ERROR"));
+    let message = reg_str(".*");
+
+    seq((date.and_l(spaces()), level.and_l(spaces()), message)).parse(input)
+}
+
+#[test]
+fn test_str_outputs_borrow_input() {
+    let inp = String::from("2023-01-02 WARN disk almost full");
+
+    let ((date, level, message), rest) = log_line_p(&inp).unwrap();
+    assert_eq!(
+        ("2023-01-02", "WARN", "disk almost full", ""),
+        (date, level, message, rest)
+    );
+
+    // the outputs are slices of the input, not copies
+    assert_eq!(inp.as_ptr(), date.as_ptr());
+    assert_eq!(inp[16..].as_ptr(), message.as_ptr());
+
+    let err = log_line_p("2023-01-02 DEBUG x").unwrap_err();
+    assert_eq!(vec!["`INFO`", "`WARN`", "`ERROR`"], err.expected);
+}
+
+#[test]
+fn test_boxed_and_memoized_str_parsers() {
+    let inp = String::from("let x = y;");
+
+    let kw: BoxedRefParser<str, &str, ParseError> = lit_str("let").boxed_ref();
+    let (out, rest) = parse(kw.and_l(spaces()), &inp[..]).unwrap();
+    assert_eq!(("let", "x = y;"), (out, rest));
+    assert_eq!(inp.as_ptr(), out.as_ptr());
+
+    // the second alternative reuses the word matched by the first
+    let calls = Cell::new(0);
+    let counted = |input| {
+        calls.set(calls.get() + 1);
+        reg_str(r"\w+").parse(input)
+    };
+    let word = counted.memo_slice("word").rc();
+    let assign = (word.clone().and_l(lit_str(" =="))).or(word.clone().and_l(lit_str(" =")));
+
+    let (out, rest) = parse(assign, &inp[4..]).unwrap();
+    assert_eq!(("x", " y;"), (out, rest));
+    assert_eq!(inp[4..].as_ptr(), out.as_ptr());
+    assert_eq!(1, calls.get());
+
+    let err = parse(word.boxed_ref(), &inp[7..]).unwrap_err();
+    assert_eq!(vec![r"/\w+/"], err.expected);
+}
+
+#[test]
+fn test_recursive_str_parser() {
+    // list = "[" (word | list)* "]", keeping the words as slices
+    let inp = String::from("[a [b c] [] d]");
+    let list = recursive_ref(|list| {
+        let item = reg_str(r"\w+").map(|w| vec![w]).or(list);
+
+        lit_str("[")
+            .and_r(item.and_l(spaces()).repeat())
+            .and_l(lit_str("]"))
+            .map(|items| items.concat())
+    });
+
+    let (words, rest) = parse(list, &inp[..]).unwrap();
+    assert_eq!((vec!["a", "b", "c", "d"], ""), (words.clone(), rest));
+    assert_eq!(inp[1..].as_ptr(), words[0].as_ptr());
+}